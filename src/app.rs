@@ -1,5 +1,7 @@
 use clap::{App, AppSettings, Arg};
 
+use crate::wordlist::BUILTIN_LISTS;
+
 pub fn create_app() -> App<'static> {
     App::new("rdg")
         .version("0.1.1")
@@ -14,17 +16,196 @@ pub fn create_app() -> App<'static> {
                 .about("Number of values to generate, default 1")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("seed")
+                .short('s')
+                .long("seed")
+                .value_name("integer")
+                .about("Seed for reproducible output, default random")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("secure")
+                .long("secure")
+                .about("Draw directly from the operating system's secure random number generator")
+                .conflicts_with("seed"),
+        )
+        .arg(
+            Arg::new("unique")
+                .long("unique")
+                .about("Never output the same value twice, supported by int, string and word"),
+        )
+        .arg(
+            Arg::new("null-rate")
+                .long("null-rate")
+                .value_name("probability")
+                .about("Probability that a value is replaced by a missing value, default 0")
+                .takes_value(true)
+                .conflicts_with("unique"),
+        )
+        .arg(
+            Arg::new("null")
+                .long("null")
+                .value_name("string")
+                .about("Text written for a missing value, default empty, or null in JSON and NULL in SQL")
+                .takes_value(true)
+                .allow_hyphen_values(true),
+        )
         .subcommand(
             App::new("word")
-                .about("Random words, requires a wordlist")
+                .about("Random words, one per line of a wordlist")
+                .arg(
+                    Arg::new("file")
+                        .short('f')
+                        .long("file")
+                        .value_name("path")
+                        .about("Wordlist used for sampling, may be repeated, default - for stdin")
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("list")
+                        .short('l')
+                        .long("list")
+                        .value_name("name")
+                        .about("Built-in wordlist to use instead of a file")
+                        .takes_value(true)
+                        .possible_values(BUILTIN_LISTS.iter().map(|(name, _)| *name))
+                        .conflicts_with("file"),
+                )
+                .arg(
+                    Arg::new("column")
+                        .long("column")
+                        .value_name("integer")
+                        .about("Field of each line to use, counting from 1, default the whole line")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("delimiter")
+                        .long("delimiter")
+                        .value_name("char")
+                        .about("Character separating the fields of a line, default tab")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("weighted").long("weighted").about(
+                        "Draw words in proportion to the weight in the last field of their line",
+                    ),
+                ),
+        )
+        .subcommand(
+            App::new("passphrase")
+                .about("Random passphrases made of words from a wordlist")
+                .arg(
+                    Arg::new("words")
+                        .short('w')
+                        .long("words")
+                        .value_name("integer")
+                        .about("Number of words in each passphrase, default 6")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("separator")
+                        .long("separator")
+                        .value_name("string")
+                        .about("Text placed between the words, default a space")
+                        .takes_value(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    Arg::new("list")
+                        .short('l')
+                        .long("list")
+                        .value_name("name")
+                        .about("Built-in wordlist to draw from, default long")
+                        .takes_value(true)
+                        .possible_values(BUILTIN_LISTS.iter().map(|(name, _)| *name)),
+                )
                 .arg(
                     Arg::new("file")
                         .short('f')
                         .long("file")
                         .value_name("path")
-                        .about("Wordlist used for sampling")
+                        .about("Wordlist to draw from instead of a built-in one")
+                        .takes_value(true)
+                        .conflicts_with("list"),
+                )
+                .arg(
+                    Arg::new("column")
+                        .long("column")
+                        .value_name("integer")
+                        .about("Field of each line to use, counting from 1, default the whole line")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("delimiter")
+                        .long("delimiter")
+                        .value_name("char")
+                        .about("Character separating the fields of a line, default tab")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            App::new("template")
+                .about("Lines of text with placeholders replaced by random values")
+                .arg(
+                    Arg::new("template")
+                        .value_name("template")
+                        .about("Text with placeholders such as {int:1:100}, {float:0:1:.2}, {word:path} and {string:pattern}")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("unicode")
+                        .long("unicode")
+                        .about("Negated classes match any Unicode character, default printable ASCII"),
+                ),
+        )
+        .subcommand(
+            App::new("records")
+                .about("Records with the columns of a schema file")
+                .arg(
+                    Arg::new("schema")
+                        .long("schema")
+                        .value_name("path")
+                        .about("TOML file with a [[column]] table for each column")
                         .takes_value(true)
                         .required(true),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("name")
+                        .about("Output format, default csv")
+                        .takes_value(true)
+                        .possible_values(["csv", "jsonl", "sql", "tsv"]),
+                )
+                .arg(
+                    Arg::new("no-header")
+                        .long("no-header")
+                        .about("Leave out the line of column names"),
+                )
+                .arg(
+                    Arg::new("table")
+                        .long("table")
+                        .value_name("name")
+                        .about("Table into which sql inserts rows, required by sql")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("dialect")
+                        .long("dialect")
+                        .value_name("name")
+                        .about("SQL dialect, default postgres")
+                        .takes_value(true)
+                        .possible_values(["mysql", "postgres", "sqlite"]),
+                )
+                .arg(
+                    Arg::new("batch-size")
+                        .long("batch-size")
+                        .value_name("integer")
+                        .about("Rows in each INSERT statement, default 100")
+                        .takes_value(true),
                 ),
         )
         .subcommand(
@@ -37,6 +218,11 @@ pub fn create_app() -> App<'static> {
                         .value_name("string")
                         .about("Pattern from which to sample, default [A-Za-z0-9]{10}")
                         .takes_value(true),
+                )
+                .arg(
+                    Arg::new("unicode").long("unicode").about(
+                        "Negated classes match any Unicode character, default printable ASCII",
+                    ),
                 ),
         )
         .subcommand(
@@ -48,7 +234,8 @@ pub fn create_app() -> App<'static> {
                         .long("lower")
                         .value_name("integer")
                         .about("Lower bound (inclusive), default 0")
-                        .takes_value(true),
+                        .takes_value(true)
+                        .allow_hyphen_values(true),
                 )
                 .arg(
                     Arg::new("upper")
@@ -56,8 +243,70 @@ pub fn create_app() -> App<'static> {
                         .long("upper")
                         .value_name("integer")
                         .about("Upper bound (exclusive), default 2")
+                        .takes_value(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    Arg::new("inclusive")
+                        .long("inclusive")
+                        .about("Include the upper bound, which then defaults to 1"),
+                )
+                .arg(
+                    Arg::new("step")
+                        .long("step")
+                        .value_name("integer")
+                        .about("Spacing between the values of the uniform distribution, default 1")
                         .takes_value(true),
-                ),
+                )
+                .arg(
+                    Arg::new("dist")
+                        .short('d')
+                        .long("dist")
+                        .value_name("name")
+                        .about("Distribution to sample, default uniform")
+                        .takes_value(true)
+                        .possible_values([
+                            "uniform",
+                            "poisson",
+                            "binomial",
+                            "negbinomial",
+                            "geometric",
+                            "hypergeometric",
+                            "zipf",
+                        ]),
+                )
+                .arg(distribution_parameter("lambda", "Mean of the poisson distribution"))
+                .arg(distribution_parameter(
+                    "p",
+                    "Success probability for binomial, negbinomial and geometric",
+                ))
+                .arg(
+                    distribution_parameter("trials", "Number of trials for binomial")
+                        .value_name("integer"),
+                )
+                .arg(
+                    distribution_parameter(
+                        "successes",
+                        "Successes to wait for in negbinomial, or in the population for hypergeometric",
+                    )
+                    .value_name("integer"),
+                )
+                .arg(
+                    distribution_parameter("population", "Population size for hypergeometric")
+                        .value_name("integer"),
+                )
+                .arg(
+                    distribution_parameter("draws", "Number of draws for hypergeometric")
+                        .value_name("integer"),
+                )
+                .arg(
+                    distribution_parameter("elements", "Number of ranks for zipf")
+                        .value_name("integer"),
+                )
+                .arg(distribution_parameter(
+                    "exponent",
+                    "Exponent of the zipf distribution, default 1",
+                )),
         )
         .subcommand(
             App::new("float")
@@ -67,16 +316,96 @@ pub fn create_app() -> App<'static> {
                         .short('l')
                         .long("lower")
                         .value_name("integer")
-                        .about("Lower bound (inclusive), default 0")
-                        .takes_value(true),
+                        .about("Lower bound (inclusive), default 0 for the uniform distribution")
+                        .takes_value(true)
+                        .allow_hyphen_values(true),
                 )
                 .arg(
                     Arg::new("upper")
                         .short('u')
                         .long("upper")
                         .value_name("integer")
-                        .about("Upper bound (exclusive), default 1")
-                        .takes_value(true),
-                ),
+                        .about("Upper bound (exclusive), default 1 for the uniform distribution")
+                        .takes_value(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    Arg::new("dist")
+                        .short('d')
+                        .long("dist")
+                        .value_name("name")
+                        .about("Distribution to sample, default uniform; bounds truncate any other")
+                        .takes_value(true)
+                        .possible_values([
+                            "uniform",
+                            "normal",
+                            "lognormal",
+                            "exponential",
+                            "gamma",
+                            "beta",
+                            "cauchy",
+                            "t",
+                            "chisquared",
+                            "weibull",
+                            "triangular",
+                        ]),
+                )
+                .arg(distribution_parameter(
+                    "mean",
+                    "Mean of the normal distribution, or of the log for lognormal, default 0",
+                ))
+                .arg(distribution_parameter(
+                    "sd",
+                    "Standard deviation for normal and lognormal, default 1",
+                ))
+                .arg(distribution_parameter(
+                    "rate",
+                    "Rate of the exponential distribution, default 1",
+                ))
+                .arg(distribution_parameter(
+                    "shape",
+                    "Shape for gamma and weibull",
+                ))
+                .arg(distribution_parameter(
+                    "scale",
+                    "Scale for gamma, cauchy and weibull, default 1",
+                ))
+                .arg(distribution_parameter(
+                    "alpha",
+                    "First shape parameter for beta",
+                ))
+                .arg(distribution_parameter(
+                    "beta",
+                    "Second shape parameter for beta",
+                ))
+                .arg(distribution_parameter(
+                    "location",
+                    "Location of the cauchy distribution, default 0",
+                ))
+                .arg(distribution_parameter(
+                    "df",
+                    "Degrees of freedom for t and chisquared",
+                ))
+                .arg(distribution_parameter(
+                    "min",
+                    "Minimum of the triangular distribution",
+                ))
+                .arg(distribution_parameter(
+                    "max",
+                    "Maximum of the triangular distribution",
+                ))
+                .arg(distribution_parameter(
+                    "mode",
+                    "Mode of the triangular distribution, default midway between min and max",
+                )),
         )
 }
+
+fn distribution_parameter(name: &'static str, about: &'static str) -> Arg<'static> {
+    Arg::new(name)
+        .long(name)
+        .value_name("float")
+        .about(about)
+        .takes_value(true)
+        .allow_hyphen_values(true)
+}
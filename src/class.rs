@@ -0,0 +1,206 @@
+use std::iter::FromIterator;
+
+const SURROGATES: (u32, u32) = (0xD800, 0xDFFF);
+
+/// A set of characters stored as sorted, non-overlapping inclusive ranges of
+/// code points. Members are looked up by index, so large sets never need to
+/// be enumerated.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CharClass {
+    ranges: Vec<(u32, u32)>,
+}
+
+impl CharClass {
+    pub fn new() -> CharClass {
+        CharClass { ranges: vec![] }
+    }
+
+    /// Return the class of all characters between `start` and `end` inclusive.
+    pub fn from_range(start: char, end: char) -> CharClass {
+        let mut class = CharClass::new();
+        class.push_range(u32::from(start), u32::from(end));
+        class.normalize();
+        class
+    }
+
+    /// Return the class of all characters lying in any of the given inclusive
+    /// ranges.
+    pub fn from_ranges<I: IntoIterator<Item = (char, char)>>(ranges: I) -> CharClass {
+        let mut class = CharClass::new();
+        for (start, end) in ranges {
+            class.push_range(u32::from(start), u32::from(end));
+        }
+        class.normalize();
+        class
+    }
+
+    /// Return the class of printable ASCII characters, space through tilde.
+    pub fn printable_ascii() -> CharClass {
+        CharClass::from_range(' ', '~')
+    }
+
+    /// Return the class of every Unicode scalar value.
+    pub fn unicode() -> CharClass {
+        CharClass::from_range('\0', char::MAX)
+    }
+
+    pub fn union(&self, other: &CharClass) -> CharClass {
+        let mut class = CharClass {
+            ranges: self.ranges.clone(),
+        };
+        class.ranges.extend(&other.ranges);
+        class.normalize();
+        class
+    }
+
+    /// Return the characters of this class which are not in `other`.
+    pub fn difference(&self, other: &CharClass) -> CharClass {
+        let mut ranges = vec![];
+        for &(start, end) in &self.ranges {
+            let mut next = start;
+            for &(other_start, other_end) in &other.ranges {
+                if other_end < next || other_start > end {
+                    continue;
+                }
+                if other_start > next {
+                    ranges.push((next, other_start - 1));
+                }
+                next = other_end.saturating_add(1);
+                if next > end {
+                    break;
+                }
+            }
+            if next <= end {
+                ranges.push((next, end));
+            }
+        }
+        CharClass { ranges }
+    }
+
+    /// Return the number of characters in the class.
+    pub fn len(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|(start, end)| u64::from(end - start) + 1)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Return the character at position `idx` when the members of the class
+    /// are listed in code point order.
+    pub fn nth(&self, idx: u64) -> Option<char> {
+        let mut idx = idx;
+        for &(start, end) in &self.ranges {
+            let size = u64::from(end - start) + 1;
+            if idx < size {
+                return char::from_u32(start + idx as u32);
+            }
+            idx -= size;
+        }
+        None
+    }
+
+    fn push_range(&mut self, start: u32, end: u32) {
+        if start > end {
+            return;
+        }
+        if end < SURROGATES.0 || start > SURROGATES.1 {
+            self.ranges.push((start, end));
+            return;
+        }
+        if start < SURROGATES.0 {
+            self.ranges.push((start, SURROGATES.0 - 1));
+        }
+        if end > SURROGATES.1 {
+            self.ranges.push((SURROGATES.1 + 1, end));
+        }
+    }
+
+    /// Sort the ranges and merge any that overlap or touch.
+    fn normalize(&mut self) {
+        self.ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = vec![];
+        for &(start, end) in &self.ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => {
+                    last.1 = last.1.max(end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        self.ranges = merged;
+    }
+}
+
+impl FromIterator<char> for CharClass {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> CharClass {
+        let mut class = CharClass::new();
+        for c in iter {
+            class.push_range(u32::from(c), u32::from(c));
+        }
+        class.normalize();
+        class
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_iter_merges_characters() {
+        let class = "cabzyc".chars().collect::<CharClass>();
+        assert_eq!(class.ranges, vec![(97, 99), (121, 122)]);
+        assert_eq!(class.len(), 5);
+    }
+
+    #[test]
+    fn ranges_skip_surrogates() {
+        let class = CharClass::unicode();
+        assert_eq!(class.len(), 0x110000 - 0x800);
+        assert_eq!(class.nth(0xD7FF), Some('\u{D7FF}'));
+        assert_eq!(class.nth(0xD800), Some('\u{E000}'));
+        assert_eq!(class.nth(class.len() - 1), Some(char::MAX));
+        assert_eq!(class.nth(class.len()), None);
+    }
+
+    #[test]
+    fn from_ranges_merges_ranges() {
+        let class = CharClass::from_ranges(vec![('x', 'z'), ('a', 'c'), ('b', 'f'), ('g', 'g')]);
+        assert_eq!(class.ranges, vec![(97, 103), (120, 122)]);
+        assert_eq!(class.len(), 10);
+    }
+
+    #[test]
+    fn check_union() {
+        let class = CharClass::from_range('a', 'f').union(&CharClass::from_range('d', 'k'));
+        assert_eq!(class, CharClass::from_range('a', 'k'));
+        let class = CharClass::from_range('a', 'c').union(&CharClass::from_range('x', 'z'));
+        assert_eq!(class.len(), 6);
+    }
+
+    #[test]
+    fn check_difference() {
+        let vowels = "aeiou".chars().collect::<CharClass>();
+        let consonants = CharClass::from_range('a', 'z').difference(&vowels);
+        assert_eq!(consonants.len(), 21);
+        for idx in 0..consonants.len() {
+            assert!(!"aeiou".contains(consonants.nth(idx).unwrap()));
+        }
+        assert!(CharClass::printable_ascii()
+            .difference(&CharClass::printable_ascii())
+            .is_empty());
+        assert_eq!(CharClass::new().difference(&vowels), CharClass::new());
+    }
+
+    #[test]
+    fn check_nth() {
+        let class = "az".chars().collect::<CharClass>();
+        assert_eq!(class.nth(0), Some('a'));
+        assert_eq!(class.nth(1), Some('z'));
+        assert_eq!(class.nth(2), None);
+    }
+}
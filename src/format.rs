@@ -0,0 +1,298 @@
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+
+use crate::schema::Value;
+
+/// A way of writing records, one per line. A missing value is an empty field
+/// in CSV and TSV and `null` in JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Comma-separated values as in RFC 4180, quoting any field that holds a
+    /// comma, a double quote or a line break.
+    Csv,
+    /// Tab-separated values, with tabs, line breaks and backslashes in a field
+    /// escaped as `\t`, `\n`, `\r` and `\\`.
+    Tsv,
+    /// One JSON object per line with the columns as keys, in order.
+    JsonLines,
+}
+
+impl Format {
+    /// Return the line naming the columns, for formats that have one.
+    pub fn header(&self, names: &[&str]) -> Option<String> {
+        match self {
+            Format::Csv => Some(join(names.iter().map(|name| csv_field(name)), ",")),
+            Format::Tsv => Some(join(names.iter().map(|name| tsv_field(name)), "\t")),
+            Format::JsonLines => None,
+        }
+    }
+
+    pub fn record(&self, names: &[&str], values: &[Value]) -> String {
+        match self {
+            Format::Csv => join(values.iter().map(|value| csv_field(value.text())), ","),
+            Format::Tsv => join(values.iter().map(|value| tsv_field(value.text())), "\t"),
+            Format::JsonLines => {
+                let members = names.iter().zip(values).map(|(name, value)| {
+                    let value = match value {
+                        Value::Number(number) => Cow::Borrowed(number.as_str()),
+                        Value::Text(text) => Cow::Owned(json_string(text)),
+                        Value::Null => Cow::Borrowed("null"),
+                    };
+                    Cow::Owned(format!("{}:{}", json_string(name), value))
+                });
+                format!("{{{}}}", join(members, ","))
+            }
+        }
+    }
+}
+
+/// A database whose SQL is written by `--format sql`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    PostgreSql,
+    MySql,
+    Sqlite,
+}
+
+impl Dialect {
+    /// Return a statement inserting the rows into the table, one row per
+    /// line. A table name containing a period is taken to be qualified by a
+    /// schema or database name, and each part is quoted separately. It is an
+    /// error for a string to hold NUL where the database cannot store one.
+    pub fn insert(
+        &self,
+        table: &str,
+        names: &[&str],
+        rows: &[Vec<Value>],
+    ) -> Result<String, NulError> {
+        let table = join(
+            table
+                .split('.')
+                .map(|part| Cow::Owned(self.identifier(part))),
+            ".",
+        );
+        let columns = join(
+            names.iter().map(|name| Cow::Owned(self.identifier(name))),
+            ", ",
+        );
+        let mut lines = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut literals = Vec::with_capacity(row.len());
+            for (name, value) in names.iter().zip(row) {
+                literals.push(self.literal(value).ok_or_else(|| NulError {
+                    column: name.to_string(),
+                })?);
+            }
+            lines.push(Cow::Owned(format!("({})", literals.join(", "))));
+        }
+        let rows = join(lines.into_iter(), ",\n");
+        Ok(format!(
+            "INSERT INTO {} ({}) VALUES\n{};",
+            table, columns, rows
+        ))
+    }
+
+    fn identifier(&self, name: &str) -> String {
+        match self {
+            Dialect::MySql => format!("`{}`", name.replace('`', "``")),
+            Dialect::PostgreSql | Dialect::Sqlite => format!("\"{}\"", name.replace('"', "\"\"")),
+        }
+    }
+
+    /// Return a value as a literal. Numbers and NULL are written as they are,
+    /// and strings are quoted. MySQL treats a backslash in a string as an escape
+    /// unless told otherwise, so there backslashes and NUL are escaped too.
+    /// PostgreSQL text cannot hold NUL at all, so such a string gives None.
+    fn literal<'a>(&self, value: &'a Value) -> Option<Cow<'a, str>> {
+        let text = match value {
+            Value::Number(number) => return Some(Cow::Borrowed(number)),
+            Value::Text(text) => text,
+            Value::Null => return Some(Cow::Borrowed("NULL")),
+        };
+        if *self == Dialect::PostgreSql && text.contains('\0') {
+            return None;
+        }
+        let mut quoted = String::with_capacity(text.len() + 2);
+        quoted.push('\'');
+        for c in text.chars() {
+            match (self, c) {
+                (_, '\'') => quoted.push_str("''"),
+                (Dialect::MySql, '\\') => quoted.push_str("\\\\"),
+                (Dialect::MySql, '\0') => quoted.push_str("\\0"),
+                _ => quoted.push(c),
+            }
+        }
+        quoted.push('\'');
+        Some(Cow::Owned(quoted))
+    }
+}
+
+/// A string bound for a column of a database that cannot store the NUL it
+/// holds.
+#[derive(Debug, PartialEq)]
+pub struct NulError {
+    pub column: String,
+}
+
+impl fmt::Display for NulError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "column {} holds a NUL character, which PostgreSQL text cannot store",
+            self.column
+        )
+    }
+}
+
+fn join<'a, I: Iterator<Item = Cow<'a, str>>>(fields: I, separator: &str) -> String {
+    fields.collect::<Vec<_>>().join(separator)
+}
+
+fn csv_field(text: &str) -> Cow<'_, str> {
+    if text.contains(&[',', '"', '\n', '\r'][..]) {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+fn tsv_field(text: &str) -> Cow<'_, str> {
+    if !text.contains(&['\t', '\n', '\r', '\\'][..]) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 2);
+    for c in text.chars() {
+        match c {
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\\' => escaped.push_str("\\\\"),
+            _ => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Vec<Value> {
+        vec![
+            Value::Number(String::from("-12")),
+            Value::Text(String::from("Doe, \"Jo\"")),
+            Value::Text(String::from("a\tb\\c\nd\u{1}é")),
+        ]
+    }
+
+    #[test]
+    fn csv_quotes_special_fields() {
+        let names = ["id", "name, full", "note"];
+        assert_eq!(
+            Format::Csv.header(&names).unwrap(),
+            "id,\"name, full\",note"
+        );
+        assert_eq!(
+            Format::Csv.record(&names, &values()),
+            "-12,\"Doe, \"\"Jo\"\"\",\"a\tb\\c\nd\u{1}é\""
+        );
+    }
+
+    #[test]
+    fn tsv_escapes_special_characters() {
+        let names = ["id", "name", "note"];
+        assert_eq!(Format::Tsv.header(&names).unwrap(), "id\tname\tnote");
+        assert_eq!(
+            Format::Tsv.record(&names, &values()),
+            "-12\tDoe, \"Jo\"\ta\\tb\\\\c\\nd\u{1}é"
+        );
+    }
+
+    #[test]
+    fn sql_quotes_identifiers_and_strings() {
+        let names = ["id", "na\"me`", "note"];
+        let rows = vec![
+            values(),
+            vec![
+                Value::Number(String::from("7")),
+                Value::Text(String::from("O'Neil")),
+                Value::Text(String::from("x\0y")),
+            ],
+        ];
+        assert_eq!(
+            Dialect::PostgreSql.insert("public.users", &names, &rows[..1]),
+            Ok(String::from(
+                "INSERT INTO \"public\".\"users\" (\"id\", \"na\"\"me`\", \"note\") VALUES\n\
+                 (-12, 'Doe, \"Jo\"', 'a\tb\\c\nd\u{1}é');"
+            ))
+        );
+        assert_eq!(
+            Dialect::PostgreSql.insert("public.users", &names, &rows),
+            Err(NulError {
+                column: String::from("note")
+            })
+        );
+        assert_eq!(
+            Dialect::Sqlite.insert(
+                "users",
+                &names[..1],
+                &[vec![Value::Number(String::from("1"))]]
+            ),
+            Ok(String::from("INSERT INTO \"users\" (\"id\") VALUES\n(1);"))
+        );
+        assert_eq!(
+            Dialect::MySql.insert("users", &names, &rows),
+            Ok(String::from(
+                "INSERT INTO `users` (`id`, `na\"me```, `note`) VALUES\n\
+                 (-12, 'Doe, \"Jo\"', 'a\tb\\\\c\nd\u{1}é'),\n\
+                 (7, 'O''Neil', 'x\\0y');"
+            ))
+        );
+    }
+
+    #[test]
+    fn json_lines_quote_only_text() {
+        let names = ["id", "na\"me", "note"];
+        assert_eq!(Format::JsonLines.header(&names), None);
+        assert_eq!(
+            Format::JsonLines.record(&names, &values()),
+            r#"{"id":-12,"na\"me":"Doe, \"Jo\"","note":"a\tb\\c\nd\u0001é"}"#
+        );
+    }
+
+    #[test]
+    fn missing_values_are_empty_or_null() {
+        let names = ["id", "name"];
+        let values = vec![Value::Null, Value::Text(String::from("Ann"))];
+        assert_eq!(Format::Csv.record(&names, &values), ",Ann");
+        assert_eq!(Format::Tsv.record(&names, &values), "\tAnn");
+        assert_eq!(
+            Format::JsonLines.record(&names, &values),
+            r#"{"id":null,"name":"Ann"}"#
+        );
+        assert_eq!(
+            Dialect::Sqlite.insert("users", &names, &[values]),
+            Ok(String::from(
+                "INSERT INTO \"users\" (\"id\", \"name\") VALUES\n(NULL, 'Ann');"
+            ))
+        );
+    }
+}
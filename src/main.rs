@@ -1,10 +1,32 @@
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
 use std::process;
+use std::str::FromStr;
+
+use clap::ArgMatches;
+
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
 mod app;
+mod class;
+mod format;
 mod pattern;
 mod sample;
+mod schema;
+mod template;
+mod wordlist;
 
-use pattern::Pattern;
+use class::CharClass;
+use format::{Dialect, Format};
+use pattern::{Pattern, SamplerError};
+use sample::{AliasTable, DistinctError, FloatDistribution, IntDistribution, TruncatedFloat};
+use schema::{Schema, Value};
+use template::Template;
+use wordlist::{StreamSample, Wordlist};
 
 const EXIT_SUCCESS: i32 = 0;
 const EXIT_ERROR: i32 = 1;
@@ -19,87 +41,540 @@ fn main() {
             eprintln!("Error parsing count: {}", err);
             process::exit(EXIT_ERROR);
         });
+    let mut rng: Box<dyn RngCore> = match app_matches.value_of("seed") {
+        _ if app_matches.is_present("secure") => Box::new(OsRng),
+        Some(seed) => Box::new(ChaCha20Rng::seed_from_u64(
+            seed.parse::<u64>().unwrap_or_else(|err| {
+                eprintln!("Error parsing seed: {}", err);
+                process::exit(EXIT_ERROR);
+            }),
+        )),
+        None => Box::new(ChaCha20Rng::from_entropy()),
+    };
+    let unique = app_matches.is_present("unique");
+    let null_rate = app_matches
+        .value_of("null-rate")
+        .unwrap_or("0")
+        .parse::<f64>()
+        .unwrap_or_else(|err| {
+            eprintln!("Error parsing null-rate: {}", err);
+            process::exit(EXIT_ERROR);
+        });
+    if !sample::valid_null_rate(null_rate) {
+        eprintln!("Error: null-rate must be between 0 and 1");
+        process::exit(EXIT_ERROR);
+    }
+    let null = app_matches.value_of("null");
 
     match app_matches.subcommand() {
         Some(("word", word_matches)) => {
-            let file = word_matches.value_of("file").unwrap();
+            let list = word_matches.value_of("list");
+            let files: Vec<&str> = match word_matches.values_of("file") {
+                Some(files) => files.collect(),
+                None if list.is_some() => vec![],
+                None => vec!["-"],
+            };
+            let weighted = word_matches.is_present("weighted");
+            let delimiter = parse_delimiter(word_matches);
+            let column = parse_column(word_matches, delimiter);
+            if word_matches.is_present("delimiter") && column.is_none() && !weighted {
+                eprintln!("Error: --delimiter only applies with --column or --weighted");
+                process::exit(EXIT_ERROR);
+            }
+            let exit_with_read_error = |err: io::Error| -> ! {
+                eprintln!("Error reading file: {}", err);
+                process::exit(EXIT_ERROR);
+            };
+            let exit_with_no_words = || -> ! {
+                if weighted {
+                    eprintln!("Error: wordlist contains no words with a positive weight");
+                } else {
+                    eprintln!("Error: wordlist contains no words");
+                }
+                process::exit(EXIT_ERROR);
+            };
+            let is_stream = |file: &str| {
+                file == "-"
+                    || !fs::metadata(file)
+                        .unwrap_or_else(|err| exit_with_read_error(err))
+                        .is_file()
+            };
+            if files.iter().any(|file| is_stream(file)) {
+                let readers: Vec<Box<dyn BufRead>> = files
+                    .iter()
+                    .map(|&file| -> Box<dyn BufRead> {
+                        if file == "-" {
+                            Box::new(io::stdin().lock())
+                        } else {
+                            Box::new(BufReader::new(
+                                File::open(file).unwrap_or_else(|err| exit_with_read_error(err)),
+                            ))
+                        }
+                    })
+                    .collect();
+                let lines = readers.into_iter().flat_map(BufRead::lines);
+                let mut words = if weighted {
+                    let words = lines.filter_map(|line| {
+                        line.and_then(|line| {
+                            let word = wordlist::weighted_word(&line, delimiter, column.as_ref())?;
+                            Ok(word.map(|(word, weight)| (word.into_owned(), weight)))
+                        })
+                        .transpose()
+                    });
+                    if unique {
+                        wordlist::sample_weighted_stream_distinct(words, count as usize, &mut rng)
+                            .map(|words| StreamSample::Drawn(words.into_iter()))
+                    } else {
+                        wordlist::sample_weighted_stream(words, count as usize, &mut rng)
+                    }
+                } else {
+                    let words = lines.filter_map(|line| match (line, column) {
+                        (Ok(line), Some(column)) => column.field(&line).map(|f| Ok(f.into_owned())),
+                        (line, None) => Some(line),
+                        (Err(err), _) => Some(Err(err)),
+                    });
+                    if unique {
+                        wordlist::sample_stream_distinct(words, count as usize, &mut rng)
+                            .map(|words| StreamSample::Drawn(words.into_iter()))
+                    } else {
+                        wordlist::sample_stream(words, count as usize, &mut rng)
+                    }
+                }
+                .unwrap_or_else(|err| exit_with_read_error(err));
+                if words.is_empty() && count > 0 {
+                    exit_with_no_words();
+                }
+                if let StreamSample::Drawn(draws) = &words {
+                    if (draws.len() as u64) < count {
+                        exit_with_too_few_values(count, "words in the wordlist", draws.len());
+                    }
+                }
+                for _ in 0..count {
+                    let word = words.draw(&mut rng).unwrap();
+                    print_value(word, null_rate, null, &mut rng);
+                }
+                process::exit(EXIT_SUCCESS);
+            }
+
+            let mut wordlist = list.map_or_else(Wordlist::new, builtin_wordlist);
+            for file in files {
+                wordlist.append(
+                    Wordlist::from_file(file).unwrap_or_else(|err| exit_with_read_error(err)),
+                );
+            }
+            if weighted {
+                let (words, weights) = wordlist
+                    .split_weights(delimiter, column.as_ref())
+                    .unwrap_or_else(|err| exit_with_read_error(err));
+                if unique {
+                    let indexes = wordlist::sample_weighted_stream_distinct(
+                        weights.iter().enumerate().map(|(idx, &w)| Ok((idx, w))),
+                        count as usize,
+                        &mut rng,
+                    )
+                    .unwrap_or_else(|err| exit_with_read_error(err));
+                    if indexes.is_empty() && count > 0 {
+                        exit_with_no_words();
+                    }
+                    if (indexes.len() as u64) < count {
+                        exit_with_too_few_values(
+                            count,
+                            "words with a positive weight",
+                            indexes.len(),
+                        );
+                    }
+                    for idx in indexes {
+                        println!("{}", words.get(idx));
+                    }
+                    process::exit(EXIT_SUCCESS);
+                }
+                let table = AliasTable::new(&weights).unwrap_or_else(|| exit_with_no_words());
+                for _ in 0..count {
+                    print_value(words.get(table.sample(&mut rng)), null_rate, null, &mut rng);
+                }
+                process::exit(EXIT_SUCCESS);
+            }
+            if let Some(column) = column {
+                wordlist = wordlist.select(&column);
+            }
+            if wordlist.is_empty() {
+                exit_with_no_words();
+            }
+            if unique {
+                if count > wordlist.len() as u64 {
+                    exit_with_too_few_values(count, "words in the wordlist", wordlist.len());
+                }
+                let indexes =
+                    sample::distinct_integers(wordlist.len() as u128 - 1, count as usize, &mut rng)
+                        .unwrap_or_else(|err| {
+                            exit_with_distinct_error(err, count, "words in the wordlist")
+                        });
+                for idx in indexes {
+                    println!("{}", wordlist.get(idx as usize));
+                }
+                process::exit(EXIT_SUCCESS);
+            }
+            for _ in 0..count {
+                print_value(wordlist.sample(&mut rng), null_rate, null, &mut rng);
+            }
+        }
+        Some(("passphrase", passphrase_matches)) => {
+            if unique {
+                eprintln!("Error: --unique is not supported for passphrase");
+                process::exit(EXIT_ERROR);
+            }
+            let words = passphrase_matches
+                .value_of("words")
+                .unwrap_or("6")
+                .parse::<usize>()
+                .unwrap_or_else(|err| {
+                    eprintln!("Error parsing words: {}", err);
+                    process::exit(EXIT_ERROR);
+                });
+            let separator = passphrase_matches.value_of("separator").unwrap_or(" ");
+            let column = parse_column(passphrase_matches, parse_delimiter(passphrase_matches));
+            if passphrase_matches.is_present("delimiter") && column.is_none() {
+                eprintln!("Error: --delimiter only applies with --column");
+                process::exit(EXIT_ERROR);
+            }
+            let mut wordlist = match passphrase_matches.value_of("file") {
+                Some(file) => Wordlist::from_file(file).unwrap_or_else(|err| {
+                    eprintln!("Error reading file: {}", err);
+                    process::exit(EXIT_ERROR);
+                }),
+                None => builtin_wordlist(passphrase_matches.value_of("list").unwrap_or("long")),
+            };
+            if let Some(column) = column {
+                wordlist = wordlist.select(&column);
+            }
+            if wordlist.is_empty() {
+                eprintln!("Error: wordlist contains no words");
+                process::exit(EXIT_ERROR);
+            }
+            eprintln!(
+                "Entropy: {:.1} bits per passphrase, {:.1} bits for each of {} words",
+                wordlist.entropy() * words as f64,
+                wordlist.entropy(),
+                words
+            );
             for _ in 0..count {
-                match sample::from_wordlist(file) {
-                    Ok(value) => println!("{}", value),
-                    Err(err) => {
-                        eprintln!("Error reading file: {}", err);
+                let passphrase = wordlist.passphrase(words, separator, &mut rng);
+                print_value(passphrase, null_rate, null, &mut rng);
+            }
+        }
+        Some(("records", records_matches)) => {
+            if unique {
+                eprintln!("Error: --unique is not supported for records");
+                process::exit(EXIT_ERROR);
+            }
+            let schema = Schema::from_file(records_matches.value_of("schema").unwrap())
+                .unwrap_or_else(|err| {
+                    eprintln!("Error in schema: {}", err);
+                    process::exit(EXIT_ERROR);
+                });
+            let names = schema.header();
+            let sample_record = |rng: &mut Box<dyn RngCore>| {
+                let values = schema.sample(null_rate, rng).unwrap_or_else(|err| {
+                    eprintln!("Error: {}", err);
+                    process::exit(EXIT_ERROR);
+                });
+                match null {
+                    Some(null) => values
+                        .into_iter()
+                        .map(|value| match value {
+                            Value::Null => Value::Text(String::from(null)),
+                            value => value,
+                        })
+                        .collect(),
+                    None => values,
+                }
+            };
+            let format = match records_matches.value_of("format").unwrap_or("csv") {
+                "sql" => {
+                    let table = records_matches.value_of("table").unwrap_or_else(|| {
+                        eprintln!("Error: --format sql requires --table");
+                        process::exit(EXIT_ERROR);
+                    });
+                    let dialect = match records_matches.value_of("dialect").unwrap_or("postgres") {
+                        "mysql" => Dialect::MySql,
+                        "sqlite" => Dialect::Sqlite,
+                        _ => Dialect::PostgreSql,
+                    };
+                    let batch_size = records_matches
+                        .value_of("batch-size")
+                        .unwrap_or("100")
+                        .parse::<usize>()
+                        .unwrap_or_else(|err| {
+                            eprintln!("Error parsing batch-size: {}", err);
+                            process::exit(EXIT_ERROR);
+                        });
+                    if batch_size == 0 {
+                        eprintln!("Error: batch-size must be positive");
                         process::exit(EXIT_ERROR);
                     }
+                    let print_insert = |rows: &[Vec<Value>]| {
+                        let statement = dialect.insert(table, &names, rows).unwrap_or_else(|err| {
+                            eprintln!("Error: {}", err);
+                            process::exit(EXIT_ERROR);
+                        });
+                        println!("{}", statement);
+                    };
+                    let mut rows = vec![];
+                    for _ in 0..count {
+                        rows.push(sample_record(&mut rng));
+                        if rows.len() == batch_size {
+                            print_insert(&rows);
+                            rows.clear();
+                        }
+                    }
+                    if !rows.is_empty() {
+                        print_insert(&rows);
+                    }
+                    process::exit(EXIT_SUCCESS);
+                }
+                "jsonl" => Format::JsonLines,
+                "tsv" => Format::Tsv,
+                _ => Format::Csv,
+            };
+            if !records_matches.is_present("no-header") {
+                if let Some(header) = format.header(&names) {
+                    println!("{}", header);
                 }
             }
+            for _ in 0..count {
+                println!("{}", format.record(&names, &sample_record(&mut rng)));
+            }
+        }
+        Some(("template", template_matches)) => {
+            if unique {
+                eprintln!("Error: --unique is not supported for template");
+                process::exit(EXIT_ERROR);
+            }
+            let text = template_matches.value_of("template").unwrap();
+            let universe = if template_matches.is_present("unicode") {
+                CharClass::unicode()
+            } else {
+                CharClass::printable_ascii()
+            };
+            let template = Template::parse(text, &universe).unwrap_or_else(|err| {
+                eprintln!("Unable to parse template:\n{}", err.annotate(text));
+                process::exit(EXIT_ERROR);
+            });
+            for _ in 0..count {
+                print_value(template.sample(&mut rng), null_rate, null, &mut rng);
+            }
         }
         Some(("string", string_matches)) => {
             let pattern = string_matches
                 .value_of("pattern")
                 .unwrap_or("[A-Za-z0-9]{10}");
-            let sampler = match Pattern::parse(pattern) {
-                Ok(s) => s.to_string_sampler(),
-                Err(_) => {
-                    eprintln!("Unable to parse pattern: {}", pattern);
-                    process::exit(EXIT_ERROR);
-                }
+            let universe = if string_matches.is_present("unicode") {
+                CharClass::unicode()
+            } else {
+                CharClass::printable_ascii()
             };
+            let sampler = Pattern::parse_sampler(pattern, &universe).unwrap_or_else(|err| {
+                match err {
+                    SamplerError::Parse(err) => {
+                        eprintln!("Unable to parse pattern:\n{}", err.annotate(pattern))
+                    }
+                    err => eprintln!("Error: {}", err),
+                }
+                process::exit(EXIT_ERROR);
+            });
+            if unique {
+                if sampler.max_cardinality() < u128::from(count) {
+                    exit_with_too_few_values(
+                        count,
+                        "distinct strings matching the pattern",
+                        sampler.max_cardinality(),
+                    );
+                }
+                let strings = sampler
+                    .sample_distinct(count as usize, &mut rng)
+                    .unwrap_or_else(|| {
+                        eprintln!(
+                            "Error: no new distinct string after {} draws",
+                            sample::MAX_DUPLICATE_DRAWS
+                        );
+                        process::exit(EXIT_ERROR);
+                    });
+                for string in strings {
+                    println!("{}", string);
+                }
+                process::exit(EXIT_SUCCESS);
+            }
             for _ in 0..count {
-                println!("{}", sampler.sample());
+                print_value(sampler.sample(&mut rng), null_rate, null, &mut rng);
             }
         }
         Some(("int", int_matches)) => {
-            let lower = int_matches
-                .value_of("lower")
-                .unwrap_or("0")
-                .parse::<u64>()
-                .unwrap_or_else(|err| {
-                    eprintln!("Error parsing lower: {}", err);
+            let dist = int_matches.value_of("dist").unwrap_or("uniform");
+            if dist != "uniform" {
+                if ["lower", "upper", "inclusive", "step"]
+                    .iter()
+                    .any(|name| int_matches.is_present(name))
+                {
+                    eprintln!(
+                        "Error: --lower, --upper, --inclusive and --step only apply to the uniform distribution"
+                    );
                     process::exit(EXIT_ERROR);
-                });
-            let upper = int_matches
-                .value_of("upper")
-                .unwrap_or("2")
-                .parse::<u64>()
+                }
+                if unique {
+                    eprintln!("Error: --unique only applies to the uniform distribution");
+                    process::exit(EXIT_ERROR);
+                }
+                let distribution = IntDistribution::from_name(
+                    dist,
+                    |name| Ok::<_, Infallible>(parse_parameter(int_matches, dist, name, None)),
+                    |name, default| Ok(parse_parameter(int_matches, dist, name, default)),
+                )
                 .unwrap_or_else(|err| {
-                    eprintln!("Error parsing upper: {}", err);
+                    eprintln!("Error: {}", err);
                     process::exit(EXIT_ERROR);
                 });
+                for _ in 0..count {
+                    print_value(distribution.sample(&mut rng), null_rate, null, &mut rng);
+                }
+                process::exit(EXIT_SUCCESS);
+            }
 
-            if lower >= upper {
-                eprintln!("Error: lower must be strictly less than upper");
+            let inclusive = int_matches.is_present("inclusive");
+            let lower = int_matches.value_of("lower").unwrap_or("0");
+            let upper = int_matches
+                .value_of("upper")
+                .unwrap_or(if inclusive { "1" } else { "2" });
+            let step = parse_parameter::<u128>(int_matches, dist, "step", Some(1));
+            if step == 0 {
+                eprintln!("Error: step must be at least 1");
                 process::exit(EXIT_ERROR);
             }
 
-            for _ in 0..count {
-                println!("{}", sample::integer_given_bounds(lower, upper));
-            }
-        }
-        Some(("float", float_matches)) => {
-            let lower = float_matches
-                .value_of("lower")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or_else(|err| {
+            if let (Ok(lower), Ok(upper)) = (lower.parse::<i128>(), upper.parse::<i128>()) {
+                let upper = closed_upper_bound(lower, upper, inclusive, upper.checked_sub(1));
+                if unique {
+                    let values = sample::distinct_signed_integers_given_step(
+                        lower,
+                        upper,
+                        step,
+                        count as usize,
+                        &mut rng,
+                    )
+                    .unwrap_or_else(|err| {
+                        exit_with_distinct_error(err, count, "integers in the range")
+                    });
+                    for value in values {
+                        println!("{}", value);
+                    }
+                    process::exit(EXIT_SUCCESS);
+                }
+                for _ in 0..count {
+                    print_value(
+                        sample::signed_integer_given_step(lower, upper, step, &mut rng),
+                        null_rate,
+                        null,
+                        &mut rng,
+                    );
+                }
+            } else if lower.parse::<i128>().is_ok_and(|lower| lower < 0) {
+                match upper.parse::<u128>() {
+                    Ok(_) => eprintln!(
+                        "Error: a negative lower and an upper above {} do not fit in one 128-bit integer type",
+                        i128::MAX
+                    ),
+                    Err(err) => eprintln!("Error parsing upper: {}", err),
+                }
+                process::exit(EXIT_ERROR);
+            } else {
+                let lower = lower.parse::<u128>().unwrap_or_else(|err| {
                     eprintln!("Error parsing lower: {}", err);
                     process::exit(EXIT_ERROR);
                 });
-            let upper = float_matches
-                .value_of("upper")
-                .unwrap_or("1")
-                .parse::<f64>()
-                .unwrap_or_else(|err| {
+                let upper = upper.parse::<u128>().unwrap_or_else(|err| {
                     eprintln!("Error parsing upper: {}", err);
                     process::exit(EXIT_ERROR);
                 });
+                let upper = closed_upper_bound(lower, upper, inclusive, upper.checked_sub(1));
+                if unique {
+                    let values = sample::distinct_unsigned_integers_given_step(
+                        lower,
+                        upper,
+                        step,
+                        count as usize,
+                        &mut rng,
+                    )
+                    .unwrap_or_else(|err| {
+                        exit_with_distinct_error(err, count, "integers in the range")
+                    });
+                    for value in values {
+                        println!("{}", value);
+                    }
+                    process::exit(EXIT_SUCCESS);
+                }
+                for _ in 0..count {
+                    print_value(
+                        sample::unsigned_integer_given_step(lower, upper, step, &mut rng),
+                        null_rate,
+                        null,
+                        &mut rng,
+                    );
+                }
+            }
+        }
+        Some(("float", float_matches)) => {
+            if unique {
+                eprintln!("Error: --unique is not supported for float");
+                process::exit(EXIT_ERROR);
+            }
+            let dist = float_matches.value_of("dist").unwrap_or("uniform");
+            let parameter =
+                |name: &str, default| parse_parameter(float_matches, dist, name, default);
+            let lower = parameter(
+                "lower",
+                Some(if dist == "uniform" {
+                    0.0
+                } else {
+                    f64::NEG_INFINITY
+                }),
+            );
+            let upper = parameter(
+                "upper",
+                Some(if dist == "uniform" {
+                    1.0
+                } else {
+                    f64::INFINITY
+                }),
+            );
 
             if lower >= upper {
                 eprintln!("Error: lower must be strictly less than upper");
                 process::exit(EXIT_ERROR);
             }
 
+            let distribution = FloatDistribution::from_name(dist, |name, default| {
+                Ok::<_, Infallible>(parameter(name, default))
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("Error: {}", err);
+                process::exit(EXIT_ERROR);
+            });
+            let sampler = TruncatedFloat {
+                distribution,
+                lower,
+                upper,
+            };
+
             for _ in 0..count {
-                println!("{}", sample::float_given_bounds(lower, upper));
+                match sampler.sample(&mut rng) {
+                    Some(value) => print_value(value, null_rate, null, &mut rng),
+                    None => {
+                        eprintln!(
+                            "Error: no value between lower and upper after {} draws",
+                            sample::MAX_TRUNCATION_ATTEMPTS
+                        );
+                        process::exit(EXIT_ERROR);
+                    }
+                }
             }
         }
         _ => {
@@ -108,3 +583,135 @@ fn main() {
     }
     process::exit(EXIT_SUCCESS);
 }
+
+/// Return the largest value an integer range may take, where `predecessor`
+/// is the value just below `upper`. If the range is empty then exit with an
+/// error.
+fn closed_upper_bound<T: PartialOrd>(
+    lower: T,
+    upper: T,
+    inclusive: bool,
+    predecessor: Option<T>,
+) -> T {
+    if inclusive && lower <= upper {
+        return upper;
+    }
+    if !inclusive && lower < upper {
+        if let Some(predecessor) = predecessor {
+            return predecessor;
+        }
+    }
+    if inclusive {
+        eprintln!("Error: lower must not be greater than upper");
+    } else {
+        eprintln!("Error: lower must be strictly less than upper");
+    }
+    process::exit(EXIT_ERROR);
+}
+
+/// Return the character given by --delimiter, by default a tab, or exit with
+/// an error if it is not a single character.
+fn parse_delimiter(matches: &ArgMatches) -> char {
+    matches.value_of("delimiter").map_or('\t', |delimiter| {
+        let mut chars = delimiter.chars();
+        match (chars.next(), chars.next()) {
+            (Some(delimiter), None) => delimiter,
+            _ => {
+                eprintln!("Error: delimiter must be a single character");
+                process::exit(EXIT_ERROR);
+            }
+        }
+    })
+}
+
+/// Return the field given by --column, counting from 1, or exit with an error
+/// if it is not a positive integer.
+fn parse_column(matches: &ArgMatches, delimiter: char) -> Option<wordlist::Column> {
+    matches.value_of("column").map(|column| {
+        let index = column.parse::<usize>().unwrap_or_else(|err| {
+            eprintln!("Error parsing column: {}", err);
+            process::exit(EXIT_ERROR);
+        });
+        if index == 0 {
+            eprintln!("Error: columns are counted from 1");
+            process::exit(EXIT_ERROR);
+        }
+        wordlist::Column {
+            index: index - 1,
+            delimiter,
+        }
+    })
+}
+
+/// Return the built-in wordlist with the given name, or exit with an error if
+/// there is none.
+fn builtin_wordlist(name: &str) -> Wordlist {
+    Wordlist::builtin(name).unwrap_or_else(|| {
+        if wordlist::BUILTIN_LISTS.is_empty() {
+            eprintln!("Error: rdg was built without the wordlists feature");
+        } else {
+            let names: Vec<&str> = wordlist::BUILTIN_LISTS.iter().map(|(n, _)| *n).collect();
+            eprintln!(
+                "Error: no built-in wordlist named {}, expected one of {}",
+                name,
+                names.join(", ")
+            );
+        }
+        process::exit(EXIT_ERROR);
+    })
+}
+
+/// Exit with an error saying that `count` distinct values cannot be drawn
+/// from only `available` of them.
+fn exit_with_too_few_values(count: u64, what: &str, available: impl Display) -> ! {
+    eprintln!(
+        "Error: cannot draw {} unique values from {} {}",
+        count, available, what
+    );
+    process::exit(EXIT_ERROR);
+}
+
+/// Exit with the error for why `count` distinct values cannot be drawn.
+fn exit_with_distinct_error(err: DistinctError, count: u64, what: &str) -> ! {
+    match err {
+        DistinctError::TooFewValues(available) => exit_with_too_few_values(count, what, available),
+        DistinctError::TooManyValues => {
+            eprintln!("Error: {} unique values do not fit in memory", count);
+            process::exit(EXIT_ERROR);
+        }
+    }
+}
+
+/// Print a value, or the text given by --null in its place at the given rate.
+fn print_value<R: Rng + ?Sized>(
+    value: impl Display,
+    null_rate: f64,
+    null: Option<&str>,
+    rng: &mut R,
+) {
+    if sample::is_missing(null_rate, rng) {
+        println!("{}", null.unwrap_or(""));
+    } else {
+        println!("{}", value);
+    }
+}
+
+/// Return the value of a distribution parameter, or `default` if it was not
+/// given. If the value cannot be parsed, or is missing and has no default,
+/// then exit with an error.
+fn parse_parameter<T>(matches: &ArgMatches, dist: &str, name: &str, default: Option<T>) -> T
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match matches.value_of(name) {
+        Some(value) => value.parse::<T>().unwrap_or_else(|err| {
+            eprintln!("Error parsing {}: {}", name, err);
+            process::exit(EXIT_ERROR);
+        }),
+        None => default.unwrap_or_else(|| {
+            eprintln!("Error: the {} distribution requires --{}", dist, name);
+            process::exit(EXIT_ERROR);
+        }),
+    }
+}
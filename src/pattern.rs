@@ -1,3 +1,9 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use regex_syntax::hir::{Class, HirKind};
+
+use crate::class::CharClass;
 use crate::sample;
 
 #[derive(Debug, PartialEq)]
@@ -9,56 +15,209 @@ pub struct Pattern {
 pub struct SubPattern {
     value: String,
     kind: SubPatternKind,
-    quantifier: u8,
+    quantifier: Quantifier,
+}
+
+/// Inclusive bounds on the number of times a SubPattern is repeated.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Quantifier {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// Number of repetitions allowed beyond the lower bound for the unbounded
+/// quantifiers `*`, `+` and `{m,}`.
+pub const UNBOUNDED_REPETITIONS: u32 = 8;
+
+impl Quantifier {
+    pub const fn exactly(n: u32) -> Quantifier {
+        Quantifier { min: n, max: n }
+    }
+
+    fn at_least(n: u32) -> Quantifier {
+        Quantifier {
+            min: n,
+            max: n.saturating_add(UNBOUNDED_REPETITIONS),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
 enum SubPatternKind {
     Literal,
-    Brackets,
-    Parentheses { pipe_positions: Option<Vec<usize>> },
+    Brackets {
+        negated: bool,
+    },
+    Parentheses {
+        alternatives: Vec<Pattern>,
+        weights: Vec<u32>,
+    },
 }
 
-#[derive(Debug)]
-pub struct ParseError;
+/// The reason a pattern could not be parsed together with the position of
+/// the offending character. Within the parser `offset` is a byte index into
+/// the string being parsed, while errors returned by `Pattern::parse` count
+/// characters from the start of the whole pattern.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub offset: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ParseErrorKind {
+    UnbalancedParenthesis,
+    UnbalancedBracket,
+    InvalidQuantifier,
+    InvalidRange,
+    InvalidEscape,
+    TrailingEscape,
+    UnknownClass,
+    UnescapedCharacter,
+    InvalidWeight,
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, offset: usize) -> ParseError {
+        ParseError { kind, offset }
+    }
+
+    /// Return the error with its offset moved `by` bytes to the right, for
+    /// errors raised while parsing a substring.
+    fn shift(self, by: usize) -> ParseError {
+        ParseError {
+            offset: self.offset + by,
+            ..self
+        }
+    }
+
+    /// Return the pattern with a caret beneath the character at fault
+    /// followed by a description of the error.
+    pub fn annotate(&self, pattern: &str) -> String {
+        annotate(pattern, self.offset, self.kind)
+    }
+}
+
+/// Return the text with a caret beneath the character at `offset` followed
+/// by the message.
+pub fn annotate(text: &str, offset: usize, message: impl fmt::Display) -> String {
+    format!(
+        "    {}\n    {}^\nerror: {}",
+        text,
+        " ".repeat(offset),
+        message
+    )
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at character {}", self.kind, self.offset)
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            ParseErrorKind::UnbalancedParenthesis => "unbalanced parenthesis",
+            ParseErrorKind::UnbalancedBracket => "unbalanced bracket",
+            ParseErrorKind::InvalidQuantifier => "invalid quantifier",
+            ParseErrorKind::InvalidRange => "range ends before it starts",
+            ParseErrorKind::InvalidEscape => "invalid escape sequence",
+            ParseErrorKind::TrailingEscape => "trailing escape character",
+            ParseErrorKind::UnknownClass => "unknown character class",
+            ParseErrorKind::UnescapedCharacter => "special character must be escaped",
+            ParseErrorKind::InvalidWeight => "invalid alternative weight",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// The reason a pattern cannot be sampled: it does not parse, it has a
+/// character class that matches no characters, or it can produce strings
+/// longer than `sample::MAX_STRING_LENGTH`.
+#[derive(Debug, PartialEq)]
+pub enum SamplerError {
+    Parse(ParseError),
+    EmptyClass,
+    TooLong,
+}
+
+impl fmt::Display for SamplerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SamplerError::Parse(err) => write!(f, "{}", err),
+            SamplerError::EmptyClass => write!(
+                f,
+                "pattern contains a character class that matches no characters"
+            ),
+            SamplerError::TooLong => write!(
+                f,
+                "pattern can produce strings longer than {} characters",
+                sample::MAX_STRING_LENGTH
+            ),
+        }
+    }
+}
 
 impl Pattern {
     pub fn parse(string: &str) -> Result<Pattern, ParseError> {
-        let mut subpatterns: Vec<SubPattern> = vec![];
-        let mut idx: usize = 0;
-        while idx < string.len() {
-            match pop_subpattern(&string[idx..]) {
-                Some((p, i)) => {
-                    idx += i + 1;
-                    subpatterns.push(p);
-                }
-                None => return Err(ParseError),
-            }
+        parse_subpatterns(string).map_err(|e| ParseError {
+            offset: string[..e.offset].chars().count(),
+            ..e
+        })
+    }
+
+    /// Parse a pattern and return a sampler for it that is known to produce
+    /// strings, none of them longer than `sample::MAX_STRING_LENGTH`.
+    pub fn parse_sampler(
+        string: &str,
+        universe: &CharClass,
+    ) -> Result<sample::StringSampler, SamplerError> {
+        let sampler = Pattern::parse(string)
+            .map_err(SamplerError::Parse)?
+            .to_string_sampler(universe);
+        if sampler.has_empty_support() {
+            return Err(SamplerError::EmptyClass);
+        }
+        if sampler.max_length() > sample::MAX_STRING_LENGTH {
+            return Err(SamplerError::TooLong);
         }
-        Ok(Pattern { subpatterns })
+        Ok(sampler)
     }
 
-    pub fn to_string_sampler(&self) -> sample::StringSampler {
-        let mut support: Vec<Vec<String>> = vec![];
-        let mut repetitions: Vec<u8> = vec![];
+    /// Return a sampler for the pattern, recursing into the alternatives
+    /// of any parenthesized groups. Negated character classes match every
+    /// character of `universe` that they do not list.
+    pub fn to_string_sampler(&self, universe: &CharClass) -> sample::StringSampler {
+        let mut support: Vec<sample::Support> = vec![];
+        let mut repetitions: Vec<RangeInclusive<u32>> = vec![];
         for p in &self.subpatterns {
-            repetitions.push(p.quantifier);
+            repetitions.push(p.quantifier.min..=p.quantifier.max);
             match &p.kind {
                 SubPatternKind::Literal => {
-                    support.push(vec![unescape(&p.value)]);
-                }
-                SubPatternKind::Brackets => {
-                    support.push(unescape(&p.value).chars().map(|c| c.to_string()).collect());
+                    support.push(sample::Support::Strings(vec![unescape(&p.value)]));
                 }
-                SubPatternKind::Parentheses { pipe_positions } => match pipe_positions {
-                    Some(pipes) => {
-                        let split_string = split_at_positions(&p.value, pipes);
-                        support.push(split_string.iter().map(|s| unescape(s)).collect());
+                SubPatternKind::Brackets { negated } => {
+                    let class = bracket_class(&p.value, universe)
+                        .expect("bracket contents are validated when parsed");
+                    if *negated {
+                        support.push(sample::Support::Class(universe.difference(&class)));
+                    } else {
+                        support.push(sample::Support::Class(class));
                     }
-                    None => {
-                        support.push(vec![unescape(&p.value)]);
-                    }
-                },
+                }
+                SubPatternKind::Parentheses {
+                    alternatives,
+                    weights,
+                } => {
+                    support.push(sample::Support::Alternatives {
+                        samplers: alternatives
+                            .iter()
+                            .map(|a| a.to_string_sampler(universe))
+                            .collect(),
+                        weights: weights.clone(),
+                    });
+                }
             }
         }
         sample::StringSampler {
@@ -68,287 +227,700 @@ impl Pattern {
     }
 }
 
+/// Parse a string as a sequence of SubPatterns, reporting the byte offset of
+/// any error.
+fn parse_subpatterns(string: &str) -> Result<Pattern, ParseError> {
+    let mut subpatterns: Vec<SubPattern> = vec![];
+    let mut idx: usize = 0;
+    while idx < string.len() {
+        let (p, i) = pop_subpattern(&string[idx..]).map_err(|e| e.shift(idx))?;
+        idx += i + 1;
+        subpatterns.push(p);
+    }
+    Ok(Pattern { subpatterns })
+}
+
 /// Return a literal SubPattern so long as the input string contains no unescaped
 /// special characters, otherwise return a ParseError.
 pub fn parse_as_literal_kind(string: &str) -> Result<SubPattern, ParseError> {
-    let mut escaped = false;
-    for (i, c) in string.chars().enumerate() {
-        if escaped {
-            escaped = false;
-            continue;
-        }
-        if is_escape_character(c) {
-            if i < string.len() - 1 {
-                escaped = true;
-                continue;
-            }
-            return Err(ParseError);
-        }
-        if is_special_character(c) {
-            return Err(ParseError);
-        }
-        escaped = false;
+    check_escaped(string, |c| {
+        is_special_character(c) || is_quantifier_character(c) || is_wildcard_character(c)
+    })?;
+    let shorthand_idx = seek_to_shorthand(string);
+    if shorthand_idx != string.len() {
+        return Err(ParseError::new(
+            ParseErrorKind::UnescapedCharacter,
+            shorthand_idx,
+        ));
     }
     Ok(SubPattern {
         kind: SubPatternKind::Literal,
         value: String::from(string),
-        quantifier: 1,
+        quantifier: Quantifier::exactly(1),
     })
 }
 
 /// Return a brackets SubPattern if possible, otherwise return a ParseError.
-/// Ranges will be expanded and enclosing brackets stripped in the value field
-/// of the SubPattern.
+/// Enclosing brackets are stripped in the value field of the SubPattern. A
+/// leading caret negates the class and is stripped too.
 pub fn parse_as_brackets_kind(string: &str) -> Result<SubPattern, ParseError> {
     let (string, q) = pop_quantifier(string);
-    let q = q.unwrap_or(1);
+    let q = q.unwrap_or(Quantifier::exactly(1));
     if !string.starts_with('[') || !string.ends_with(']') {
-        return Err(ParseError);
+        return Err(ParseError::new(ParseErrorKind::UnbalancedBracket, 0));
+    }
+    let mut inner = &string[1..(string.len() - 1)];
+    let negated = inner.starts_with('^');
+    if negated {
+        inner = &inner[1..];
     }
-    if parse_as_literal_kind(&string[1..(string.len() - 1)]).is_ok() {
+    let inner_idx = if negated { 2 } else { 1 };
+    bracket_class(inner, &CharClass::new()).map_err(|e| e.shift(inner_idx))?;
+    Ok(SubPattern {
+        value: String::from(inner),
+        kind: SubPatternKind::Brackets { negated },
+        quantifier: q,
+    })
+}
+
+/// Return a brackets SubPattern for the wildcard `.` or a shorthand class such
+/// as `\d` or `\p{Greek}`, otherwise return a ParseError. The wildcard is
+/// equivalent to `[^\n]` and each shorthand class `\x` to `[\x]`.
+pub fn parse_as_shorthand_kind(string: &str) -> Result<SubPattern, ParseError> {
+    let (string, q) = pop_quantifier(string);
+    let q = q.unwrap_or(Quantifier::exactly(1));
+    if string.len() == 1 && string.starts_with(is_wildcard_character) {
         return Ok(SubPattern {
-            value: expand_ranges(&string[1..(string.len() - 1)]),
-            kind: SubPatternKind::Brackets,
+            value: String::from("\n"),
+            kind: SubPatternKind::Brackets { negated: true },
             quantifier: q,
         });
     }
-    Err(ParseError)
+    if units(string).len() != 1 || seek_to_shorthand(string) != 0 {
+        return Err(ParseError::new(ParseErrorKind::UnescapedCharacter, 0));
+    }
+    if shorthand_class(string).is_none() {
+        return Err(ParseError::new(ParseErrorKind::UnknownClass, 0));
+    }
+    Ok(SubPattern {
+        value: String::from(string),
+        kind: SubPatternKind::Brackets { negated: false },
+        quantifier: q,
+    })
 }
 
 /// Return a parentheses SubPattern if possible, otherwise return a ParseError.
 /// Enclosing parentheses are stripped in the resulting SubPattern value field,
-/// and the indexes of unescaped pipe delimiters are stored in the `pipe_positions`
-/// field of the SubPattern.
+/// and the text between each pair of top-level pipe delimiters is parsed as a
+/// Pattern of its own and stored in the `alternatives` field. When there are
+/// at least two alternatives and every one ends in an unescaped colon and an
+/// integer, as in `(200:90|404:10)`, each is drawn with that relative weight.
+/// Otherwise the alternatives are taken literally, so `(a:1|b)` holds `a:1`,
+/// and all have weight 1.
 pub fn parse_as_parentheses_kind(string: &str) -> Result<SubPattern, ParseError> {
     let (string, q) = pop_quantifier(string);
-    let q = q.unwrap_or(1);
+    let q = q.unwrap_or(Quantifier::exactly(1));
     let indexes = find_parentheses_boundaries(string)?;
-    if indexes.len() == 2 && parse_as_literal_kind(&string[(indexes[0] + 1)..indexes[1]]).is_ok() {
-        return Ok(SubPattern {
-            value: String::from(&string[1..(string.len() - 1)]),
-            kind: SubPatternKind::Parentheses {
-                pipe_positions: None,
-            },
-            quantifier: q,
-        });
+    let texts: Vec<_> = indexes
+        .windows(2)
+        .map(|w| (w[0] + 1, &string[(w[0] + 1)..w[1]]))
+        .collect();
+    let weighted = texts.len() > 1
+        && texts
+            .iter()
+            .all(|(_, text)| !matches!(pop_weight(text).1, Ok(None)));
+    let mut alternatives = vec![];
+    let mut weights = vec![];
+    for (start, text) in texts {
+        let (alternative, weight) = if weighted {
+            let (alternative, weight) = pop_weight(text);
+            (alternative, weight.map_err(|e| e.shift(start))?.unwrap())
+        } else {
+            (text, 1)
+        };
+        alternatives.push(parse_subpatterns(alternative).map_err(|e| e.shift(start))?);
+        weights.push(weight);
     }
-    for (i, p) in indexes.iter().enumerate() {
-        if i == 0 {
-            continue;
-        }
-        if parse_as_literal_kind(&string[(indexes[i - 1] + 1)..*p]).is_err() {
-            return Err(ParseError);
-        }
+    if weights.iter().all(|&w| w == 0) {
+        return Err(ParseError::new(ParseErrorKind::InvalidWeight, 0));
     }
     Ok(SubPattern {
         value: String::from(&string[1..(string.len() - 1)]),
         kind: SubPatternKind::Parentheses {
-            pipe_positions: Some(
-                indexes[1..(indexes.len() - 1)]
-                    .iter()
-                    .map(|i| i - 1)
-                    .collect(),
-            ),
+            alternatives,
+            weights,
         },
         quantifier: q,
     })
 }
 
-/// Return the index of the first unescaped instance of any character
-/// in the `cs` vector. If none are found then return the character
-/// length of the input string.
-pub fn seek_to_unescaped(string: &str, cs: Vec<char>) -> usize {
-    let mut escaped = false;
-    for (i, a) in string.chars().enumerate() {
-        if escaped {
-            escaped = false;
-            continue;
+/// Return the alternative with any trailing weight, such as the `:90` in
+/// `200:90`, stripped off, along with the weight if there is one. Return a
+/// ParseError if the weight does not fit in a u32.
+fn pop_weight(string: &str) -> (&str, Result<Option<u32>, ParseError>) {
+    let colon_idx = units(string)
+        .into_iter()
+        .rev()
+        .find(|(_, unit)| unescaped_character(unit) == Some(':'));
+    if let Some((idx, _)) = colon_idx {
+        let digits = &string[(idx + 1)..];
+        if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+            let weight = digits
+                .parse::<u32>()
+                .map(Some)
+                .map_err(|_| ParseError::new(ParseErrorKind::InvalidWeight, idx + 1));
+            return (&string[..idx], weight);
         }
-        if is_escape_character(a) {
-            escaped = true;
-            continue;
+    }
+    (string, Ok(None))
+}
+
+/// Return the byte index of the first unescaped instance of any character
+/// in the `cs` vector. If none are found then return the byte length of the
+/// input string.
+pub fn seek_to_unescaped(string: &str, cs: Vec<char>) -> usize {
+    for (i, unit) in units(string) {
+        if unescaped_character(unit).is_some_and(|c| cs.contains(&c)) {
+            return i;
         }
-        if cs.iter().any(|b| a == *b) {
+    }
+    string.len()
+}
+
+/// Return the byte index of the escape character beginning the first
+/// shorthand class, such as `\d` or `\p{Greek}`, in the input string. If none
+/// are found then return the byte length of the input string.
+pub fn seek_to_shorthand(string: &str) -> usize {
+    for (i, unit) in units(string) {
+        if is_shorthand(unit) {
             return i;
         }
     }
     string.len()
 }
 
-/// Return the positions of the pipe delimiters in the input string so long as it's
-/// enclosed in parentheses. If not then return a ParseError.
+/// Return the byte positions of the top-level pipe delimiters in the input
+/// string, bookended by the positions of the enclosing parentheses, so long as
+/// the whole string is a single parenthesized group. If not then return a
+/// ParseError.
 pub fn find_parentheses_boundaries(string: &str) -> Result<Vec<usize>, ParseError> {
-    if !string.starts_with('(') || !string.ends_with(')') {
-        return Err(ParseError);
+    if !string.starts_with('(') || seek_to_closing_parenthesis(string) != string.len() - 1 {
+        return Err(ParseError::new(ParseErrorKind::UnbalancedParenthesis, 0));
     }
     let mut indexes: Vec<usize> = vec![0];
-    let mut escaped = false;
-    for (i, c) in string.chars().enumerate() {
-        if escaped {
-            escaped = false;
-            continue;
-        }
-        if is_escape_character(c) {
-            escaped = true;
-            continue;
-        }
-        if c == '|' {
-            indexes.push(i);
+    let mut depth = 0;
+    for (i, unit) in units(string) {
+        match unescaped_character(unit) {
+            Some('(') => depth += 1,
+            Some(')') => depth -= 1,
+            Some('|') if depth == 1 => indexes.push(i),
+            _ => {}
         }
-        escaped = false;
     }
     indexes.push(string.len() - 1);
     Ok(indexes)
 }
 
-/// Return the first SubPattern that can be parsed from the input string
-/// along with the index where the pattern ends. If no SubPattern can be
-/// extracted then return None.
-pub fn pop_subpattern(string: &str) -> Option<(SubPattern, usize)> {
-    if string.is_empty() {
-        return None;
+/// Return the byte index of the unescaped parenthesis closing the group opened
+/// at the start of the input string. If the group is never closed then return
+/// the byte length of the input string.
+fn seek_to_closing_parenthesis(string: &str) -> usize {
+    let mut depth = 0;
+    for (i, unit) in units(string) {
+        match unescaped_character(unit) {
+            Some('(') => depth += 1,
+            Some(')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    string.len()
+}
+
+/// Return the byte index of the unescaped bracket closing the class opened at
+/// the start of the input string, skipping over any POSIX classes such as
+/// `[:alpha:]` inside it. If the class is never closed then return the byte
+/// length of the input string.
+fn seek_to_closing_bracket(string: &str) -> usize {
+    for (i, unit) in bracket_units(&string[1..]) {
+        if unescaped_character(unit) == Some(']') {
+            return i + 1;
+        }
     }
-    let chars = string.chars().collect::<Vec<_>>();
-    let parse_function = |s| match chars[0] {
+    string.len()
+}
+
+/// Return the first SubPattern that can be parsed from the input string
+/// along with the byte index of its last byte. If no SubPattern can be
+/// extracted then return a ParseError. A quantifier following a literal
+/// applies only to the final character of that literal.
+pub fn pop_subpattern(string: &str) -> Result<(SubPattern, usize), ParseError> {
+    let first = match string.chars().next() {
+        Some(c) => c,
+        None => return Err(ParseError::new(ParseErrorKind::UnescapedCharacter, 0)),
+    };
+    let starts_with_shorthand = is_wildcard_character(first) || seek_to_shorthand(string) == 0;
+    let parse_function = |s| match first {
         '(' => parse_as_parentheses_kind(s),
         '[' => parse_as_brackets_kind(s),
-        _ => parse_as_literal_kind(s),
-    };
-    let closing_char = match chars[0] {
-        '(' => ')',
-        '[' => ']',
-        _ => '_',
+        _ if starts_with_shorthand => parse_as_shorthand_kind(s),
+        _ => {
+            let (s, q) = pop_quantifier(s);
+            parse_as_literal_kind(s).map(|p| SubPattern {
+                quantifier: q.unwrap_or(p.quantifier),
+                ..p
+            })
+        }
     };
-    match chars[0] {
+    let end_idx = match first {
         '(' | '[' => {
-            let end_idx = seek_to_unescaped(string, vec![closing_char]);
+            let end_idx = if first == '(' {
+                seek_to_closing_parenthesis(string)
+            } else {
+                seek_to_closing_bracket(string)
+            };
             if end_idx == string.len() {
-                return None;
+                let kind = if first == '(' {
+                    ParseErrorKind::UnbalancedParenthesis
+                } else {
+                    ParseErrorKind::UnbalancedBracket
+                };
+                return Err(ParseError::new(kind, 0));
             }
-            if end_idx == string.len() - 1
-                || (end_idx < string.len() - 1 && chars[end_idx + 1] != '{')
-            {
-                match parse_function(&string[..(end_idx + 1)]) {
-                    Ok(pattern) => return Some((pattern, end_idx)),
-                    Err(_) => return None,
-                }
+            end_idx
+        }
+        '.' => 0,
+        _ if starts_with_shorthand => units(string)[0].1.len() - 1,
+        _ => {
+            let next_idx = seek_to_unescaped(string, vec!['(', '[', '{', '?', '*', '+', '.'])
+                .min(seek_to_shorthand(string));
+            if next_idx == 0 {
+                return Err(ParseError::new(ParseErrorKind::InvalidQuantifier, 0));
             }
-            let closing_brace_idx =
-                seek_to_unescaped(&string[(end_idx + 1)..], vec!['}']) + end_idx + 1;
-            if closing_brace_idx == string.len() {
-                return None;
+            if !string[next_idx..].starts_with(is_quantifier_start) {
+                return parse_as_literal_kind(&string[..next_idx]).map(|p| (p, next_idx - 1));
             }
-            match parse_function(&string[..(closing_brace_idx + 1)]) {
-                Ok(pattern) => Some((pattern, closing_brace_idx)),
-                Err(_) => None,
+            let last_idx = seek_to_last_character(&string[..next_idx]);
+            if last_idx > 0 {
+                return parse_as_literal_kind(&string[..last_idx]).map(|p| (p, last_idx - 1));
             }
+            next_idx - 1
         }
-        _ => {
-            let next_idx = seek_to_unescaped(string, vec!['(', '[']);
-            match parse_as_literal_kind(&string[..next_idx]) {
-                Ok(pattern) => Some((pattern, next_idx - 1)),
-                Err(_) => None,
+    };
+    let quantifier_end_idx = match string[(end_idx + 1)..].chars().next() {
+        Some('?') | Some('*') | Some('+') => end_idx + 1,
+        Some('{') => {
+            let open_brace_idx = end_idx + 1;
+            let closing_brace_idx =
+                seek_to_unescaped(&string[open_brace_idx..], vec!['}']) + open_brace_idx;
+            if closing_brace_idx == string.len()
+                || parse_repetition_bounds(&string[(open_brace_idx + 1)..closing_brace_idx])
+                    .is_none()
+            {
+                return Err(ParseError::new(
+                    ParseErrorKind::InvalidQuantifier,
+                    open_brace_idx,
+                ));
             }
+            closing_brace_idx
         }
-    }
+        _ => end_idx,
+    };
+    parse_function(&string[..(quantifier_end_idx + 1)]).map(|p| (p, quantifier_end_idx))
 }
 
-/// Return the input string after stripping any quantifier strings off
-/// the tail. If such a quantifier is found then return the integer value
-/// of that string along with it.
-pub fn pop_quantifier(string: &str) -> (&str, Option<u8>) {
-    if !string.ends_with('}') {
-        return (string, None);
-    }
-    let mut previous_was_open_brace = false;
-    for (reflected_idx, c) in string.chars().rev().enumerate() {
-        let idx = string.len() - reflected_idx - 1;
-        if previous_was_open_brace && !is_escape_character(c) {
-            let parsed_quantifier = string[(idx + 2)..(string.len() - 1)].parse::<u8>();
-            match parsed_quantifier {
-                Ok(value) => return (&string[..(idx + 1)], Some(value)),
-                _ => return (string, None),
-            };
+/// Return the input string after stripping any quantifier off the tail. If
+/// such a quantifier is found then return its bounds along with it. The
+/// supported forms are `?`, `*`, `+`, `{n}`, `{m,}` and `{m,n}`.
+pub fn pop_quantifier(string: &str) -> (&str, Option<Quantifier>) {
+    let (idx, last) = match units(string).pop() {
+        Some((idx, unit)) => (idx, unescaped_character(unit)),
+        None => return (string, None),
+    };
+    let q = match last {
+        Some('?') => Quantifier { min: 0, max: 1 },
+        Some('*') => Quantifier::at_least(0),
+        Some('+') => Quantifier::at_least(1),
+        Some('}') => return pop_braced_quantifier(string),
+        _ => return (string, None),
+    };
+    (&string[..idx], Some(q))
+}
+
+fn pop_braced_quantifier(string: &str) -> (&str, Option<Quantifier>) {
+    let open_brace_idx = units(string)
+        .into_iter()
+        .rev()
+        .find(|(_, unit)| unescaped_character(unit) == Some('{'));
+    if let Some((idx, _)) = open_brace_idx {
+        if let Some(q) = parse_repetition_bounds(&string[(idx + 1)..(string.len() - 1)]) {
+            return (&string[..idx], Some(q));
         }
-        previous_was_open_brace = c == '{';
     }
     (string, None)
 }
 
-/// Return the input string with any ranges expanded into literal characters.
-fn expand_ranges(string: &str) -> String {
-    let mut result = String::from(string);
-    let mut dash_idx = seek_to_unescaped(&result, vec!['-']);
-    while 0 < dash_idx && dash_idx < result.len() - 1 {
-        let before = result.chars().nth(dash_idx - 1).unwrap();
-        let after = result.chars().nth(dash_idx + 1).unwrap();
-        if !(before.is_digit(10) && after.is_digit(10)
-            || before.is_alphabetic() && after.is_alphabetic())
-        {
-            dash_idx += seek_to_unescaped(&result[(dash_idx + 1)..], vec!['-']) + 1;
+/// Parse the contents of a braced quantifier, i.e. `n`, `m,` or `m,n`.
+fn parse_repetition_bounds(string: &str) -> Option<Quantifier> {
+    let mut bounds = string.splitn(2, ',');
+    let min = bounds.next()?.parse::<u32>().ok()?;
+    match bounds.next() {
+        None => Some(Quantifier::exactly(min)),
+        Some("") => Some(Quantifier::at_least(min)),
+        Some(max) => {
+            let max = max.parse::<u32>().ok()?;
+            if min > max {
+                return None;
+            }
+            Some(Quantifier { min, max })
+        }
+    }
+}
+
+/// Return the set of characters listed between a pair of brackets. Shorthand
+/// and POSIX classes are resolved, negated ones against `universe`, and ranges
+/// are expanded within the text between them. Return a ParseError if that
+/// text contains an unescaped special character, a range is reversed or a
+/// named class is unknown.
+fn bracket_class(string: &str, universe: &CharClass) -> Result<CharClass, ParseError> {
+    let mut class = CharClass::new();
+    let mut segment_start = 0;
+    for (i, unit) in bracket_units(string) {
+        let named_class = if is_shorthand(unit) {
+            shorthand_class(unit)
+        } else if is_posix_class(unit) {
+            posix_class(unit).map(|c| (c, false))
+        } else {
             continue;
+        };
+        class = class.union(&bracket_segment_class(
+            &string[segment_start..i],
+            segment_start,
+        )?);
+        segment_start = i + unit.len();
+        match named_class.ok_or_else(|| ParseError::new(ParseErrorKind::UnknownClass, i))? {
+            (named, true) => class = class.union(&universe.difference(&named)),
+            (named, false) => class = class.union(&named),
         }
-        let replacement = (before..=after)
-            .collect::<Vec<_>>()
-            .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>()
-            .join("");
-        if !replacement.is_empty() {
-            result.replace_range((dash_idx - 1)..(dash_idx + 2), &replacement);
+    }
+    Ok(class.union(&bracket_segment_class(
+        &string[segment_start..],
+        segment_start,
+    )?))
+}
+
+fn bracket_segment_class(string: &str, offset: usize) -> Result<CharClass, ParseError> {
+    check_escaped(string, is_special_character).map_err(|e| e.shift(offset))?;
+    expand_ranges(string).map_err(|e| e.shift(offset))
+}
+
+/// Return the units of the text between a pair of brackets, where a POSIX
+/// class such as `[:alpha:]` counts as a single unit.
+fn bracket_units(string: &str) -> Vec<(usize, &str)> {
+    let mut result = vec![];
+    let mut skip_to = 0;
+    for (i, unit) in units(string) {
+        if i < skip_to {
+            continue;
         }
-        dash_idx += seek_to_unescaped(&result[(dash_idx + 1)..], vec!['-']) + 1;
+        let len = posix_class_length(&string[i..]).unwrap_or(unit.len());
+        result.push((i, &string[i..(i + len)]));
+        skip_to = i + len;
     }
     result
 }
 
-/// Return the input string with all escape characters removed.
+/// Return the length in bytes of the POSIX class, such as `[:alpha:]`, at the
+/// start of the input string, or None if there is none.
+fn posix_class_length(string: &str) -> Option<usize> {
+    if !string.starts_with("[:") {
+        return None;
+    }
+    let name_len = string[2..].find(":]")?;
+    if name_len == 0
+        || !string[2..(2 + name_len)]
+            .chars()
+            .all(|c| c.is_ascii_alphabetic())
+    {
+        return None;
+    }
+    Some(name_len + 4)
+}
+
+fn is_posix_class(unit: &str) -> bool {
+    posix_class_length(unit) == Some(unit.len())
+}
+
+/// Return the ASCII characters matched by a POSIX class unit such as
+/// `[:alpha:]` or `[:punct:]`. If the unit names no known class then return
+/// None.
+fn posix_class(unit: &str) -> Option<CharClass> {
+    let class = match &unit[2..(unit.len() - 2)] {
+        "alpha" => CharClass::from_ranges(vec![('a', 'z'), ('A', 'Z')]),
+        "digit" => CharClass::from_range('0', '9'),
+        "alnum" => CharClass::from_ranges(vec![('a', 'z'), ('A', 'Z'), ('0', '9')]),
+        "upper" => CharClass::from_range('A', 'Z'),
+        "lower" => CharClass::from_range('a', 'z'),
+        "space" => " \t\n\r\x0B\x0C".chars().collect(),
+        "blank" => " \t".chars().collect(),
+        "punct" => CharClass::from_ranges(vec![('!', '/'), (':', '@'), ('[', '`'), ('{', '~')]),
+        "xdigit" => CharClass::from_ranges(vec![('0', '9'), ('A', 'F'), ('a', 'f')]),
+        "print" => CharClass::from_range(' ', '~'),
+        "graph" => CharClass::from_range('!', '~'),
+        "cntrl" => CharClass::from_ranges(vec![('\0', '\x1F'), ('\x7F', '\x7F')]),
+        _ => return None,
+    };
+    Some(class)
+}
+
+/// Return true if the unit is a shorthand class escape such as `\d`, `\pL` or
+/// `\P{Greek}`, whether or not it names a known class.
+fn is_shorthand(unit: &str) -> bool {
+    let mut chars = unit.chars();
+    if !chars.next().is_some_and(is_escape_character) {
+        return false;
+    }
+    match chars.next() {
+        Some('d') | Some('D') | Some('w') | Some('W') | Some('s') | Some('S') => unit.len() == 2,
+        Some('p') | Some('P') => true,
+        _ => false,
+    }
+}
+
+/// Return the characters matched by a shorthand class unit along with whether
+/// the class is negated, as `\D`, `\W`, `\S` and `\P{..}` are. The classes
+/// `\d`, `\w` and `\s` cover ASCII digits, word characters and whitespace,
+/// while `\p{..}` accepts any Unicode general category, script or binary
+/// property. If the unit names no known class then return None.
+fn shorthand_class(unit: &str) -> Option<(CharClass, bool)> {
+    let name = unit.chars().nth(1)?;
+    let class = match name.to_ascii_lowercase() {
+        'd' => CharClass::from_range('0', '9'),
+        'w' => CharClass::from_range('a', 'z')
+            .union(&CharClass::from_range('A', 'Z'))
+            .union(&CharClass::from_range('0', '9'))
+            .union(&CharClass::from_range('_', '_')),
+        's' => " \t\n\r\x0B\x0C".chars().collect(),
+        'p' => unicode_property_class(&unit[2..])?,
+        _ => return None,
+    };
+    Some((class, name.is_ascii_uppercase()))
+}
+
+/// Return the characters having the Unicode property written after `\p` in
+/// a pattern, for example `L`, `{Greek}` or `{Emoji}`.
+fn unicode_property_class(property: &str) -> Option<CharClass> {
+    let hir = regex_syntax::Parser::new()
+        .parse(&format!("\\p{}", property))
+        .ok()?;
+    match hir.kind() {
+        HirKind::Class(Class::Unicode(class)) => Some(CharClass::from_ranges(
+            class.iter().map(|range| (range.start(), range.end())),
+        )),
+        HirKind::Literal(literal) => Some(std::str::from_utf8(&literal.0).ok()?.chars().collect()),
+        _ => None,
+    }
+}
+
+/// Return the set of characters listed in the input string, where two
+/// characters joined by an unescaped dash stand for every character between
+/// them. Return a ParseError if a range ends before it starts.
+fn expand_ranges(string: &str) -> Result<CharClass, ParseError> {
+    let units = units(string);
+    let mut ranges = vec![];
+    let mut u = 0;
+    while u < units.len() {
+        let start = unit_character(units[u].1);
+        if u + 2 < units.len() && unescaped_character(units[u + 1].1) == Some('-') {
+            let end = unit_character(units[u + 2].1);
+            if start > end {
+                return Err(ParseError::new(ParseErrorKind::InvalidRange, units[u].0));
+            }
+            ranges.push((start, end));
+            u += 3;
+            continue;
+        }
+        ranges.push((start, start));
+        u += 1;
+    }
+    Ok(CharClass::from_ranges(ranges))
+}
+
+/// Return the input string with every escape sequence replaced by the
+/// character it denotes.
 fn unescape(string: &str) -> String {
     let mut result = String::from("");
-    let mut escaped = false;
-    for c in string.chars() {
-        if !escaped && is_escape_character(c) {
-            escaped = true;
-            continue;
+    for (_, unit) in units(string) {
+        match parse_escape(unit) {
+            Some((c, _)) => result.push(c),
+            None => result.push_str(unit),
         }
-        escaped = false;
-        result.push(c);
     }
     result
 }
 
-/// Return a vector of strings obtained by splitting the input at the
-/// specified positions.
-fn split_at_positions(string: &str, positions: &[usize]) -> Vec<String> {
-    if positions.is_empty() {
-        return vec![String::from(string)];
+/// Return the byte index and text of each unit of the input string, where a
+/// unit is either a single character or a whole escape sequence such as `\)`
+/// or `\u{1F600}`. A malformed hex escape with a brace, such as `\u{D800}`,
+/// is still one unit so that its brace is not taken for a quantifier.
+fn units(string: &str) -> Vec<(usize, &str)> {
+    let mut result = vec![];
+    let mut idx = 0;
+    while let Some(c) = string[idx..].chars().next() {
+        let len = match parse_escape(&string[idx..]) {
+            _ if string[idx..].starts_with("\\p") || string[idx..].starts_with("\\P") => {
+                braced_escape_length(&string[idx..])
+            }
+            Some((_, len)) => len,
+            None if string[idx..].starts_with("\\x{") || string[idx..].starts_with("\\u{") => {
+                braced_escape_length(&string[idx..])
+            }
+            None if is_escape_character(c) => {
+                c.len_utf8() + string[(idx + 1)..].chars().next().map_or(0, char::len_utf8)
+            }
+            None => c.len_utf8(),
+        };
+        result.push((idx, &string[idx..(idx + len)]));
+        idx += len;
     }
-    let mut split_string = Vec::new();
-    for (i, d) in positions.iter().enumerate() {
-        if i == 0 {
-            split_string.push(String::from(&string[0..*d]));
-        } else {
-            split_string.push(String::from(&string[(positions[i - 1] + 1)..*d]));
-        }
-        if i == positions.len() - 1 {
-            split_string.push(String::from(&string[(*d + 1)..]));
-        }
+    result
+}
+
+/// Return the length in bytes of the escape, such as `\pL`, `\p{Greek}` or
+/// `\u{1F600}`, at the start of the input string whose argument is a single
+/// character or a braced name. An unterminated brace extends the escape to the
+/// end of the string.
+fn braced_escape_length(string: &str) -> usize {
+    match string[2..].chars().next() {
+        Some('{') => string.find('}').map_or(string.len(), |i| i + 1),
+        Some(c) => 2 + c.len_utf8(),
+        None => 2,
+    }
+}
+
+/// Return the character making up a unit if it is not an escape sequence.
+fn unescaped_character(unit: &str) -> Option<char> {
+    unit.chars().next().filter(|c| !is_escape_character(*c))
+}
+
+/// Return the character denoted by a unit.
+fn unit_character(unit: &str) -> char {
+    match parse_escape(unit) {
+        Some((c, _)) => c,
+        None => unit.chars().next().unwrap(),
+    }
+}
+
+/// Return the character denoted by the escape sequence at the start of the
+/// input string along with its length in bytes, or None if there is no
+/// well-formed escape sequence there. Besides `\n`, `\r`, `\t`, `\f`, `\v`
+/// and `\0`, the forms `\xHH`, `\uHHHH`, `\x{H..}` and `\u{H..}` give a code
+/// point in hexadecimal. Any other escaped character stands for itself.
+fn parse_escape(string: &str) -> Option<(char, usize)> {
+    let mut chars = string.chars();
+    if !is_escape_character(chars.next()?) {
+        return None;
+    }
+    let c = match chars.next()? {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'f' => '\x0C',
+        'v' => '\x0B',
+        '0' => '\0',
+        'x' | 'u' => return parse_hex_escape(string),
+        c => return Some((c, 1 + c.len_utf8())),
+    };
+    Some((c, 2))
+}
+
+fn parse_hex_escape(string: &str) -> Option<(char, usize)> {
+    let (digits, len) = if string[2..].starts_with('{') {
+        let closing_brace_idx = string.find('}')?;
+        (&string[3..closing_brace_idx], closing_brace_idx + 1)
+    } else {
+        let width = if string.starts_with("\\x") { 2 } else { 4 };
+        (string.get(2..(2 + width))?, 2 + width)
+    };
+    if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let code = u32::from_str_radix(digits, 16).ok()?;
+    Some((char::from_u32(code)?, len))
+}
+
+/// Return a ParseError if the input string contains an unescaped character
+/// for which `is_reserved` holds, or a malformed or dangling escape.
+fn check_escaped(string: &str, is_reserved: fn(char) -> bool) -> Result<(), ParseError> {
+    for (i, unit) in units(string) {
+        let kind = match unescaped_character(unit) {
+            Some(c) if is_reserved(c) => reserved_character_error_kind(c),
+            None if unit.len() == 1 => ParseErrorKind::TrailingEscape,
+            None if parse_escape(unit).is_none() => ParseErrorKind::InvalidEscape,
+            _ => continue,
+        };
+        return Err(ParseError::new(kind, i));
     }
-    split_string
+    Ok(())
+}
+
+fn reserved_character_error_kind(character: char) -> ParseErrorKind {
+    match character {
+        '(' | ')' => ParseErrorKind::UnbalancedParenthesis,
+        '[' | ']' => ParseErrorKind::UnbalancedBracket,
+        '{' | '}' => ParseErrorKind::InvalidQuantifier,
+        c if is_quantifier_character(c) => ParseErrorKind::InvalidQuantifier,
+        _ => ParseErrorKind::UnescapedCharacter,
+    }
+}
+
+/// Return the byte index at which the final character of the input string
+/// begins, treating a whole escape sequence as one character.
+fn seek_to_last_character(string: &str) -> usize {
+    units(string).last().map_or(0, |(i, _)| *i)
 }
 
 fn is_special_character(character: char) -> bool {
     "()[]{}\\|".chars().any(|c| c == character)
 }
 
+fn is_quantifier_character(character: char) -> bool {
+    "?*+".chars().any(|c| c == character)
+}
+
+fn is_quantifier_start(character: char) -> bool {
+    character == '{' || is_quantifier_character(character)
+}
+
+fn is_wildcard_character(character: char) -> bool {
+    character == '.'
+}
+
 fn is_escape_character(character: char) -> bool {
     character == '\\'
 }
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+    use rand::thread_rng;
+
     use super::*;
 
+    fn literal_pattern(value: &str) -> Pattern {
+        Pattern {
+            subpatterns: vec![SubPattern {
+                value: String::from(value),
+                kind: SubPatternKind::Literal,
+                quantifier: Quantifier::exactly(1),
+            }],
+        }
+    }
+
     #[test]
     fn can_parse_as_literal_valid() {
         for s in [
@@ -360,10 +932,11 @@ mod tests {
             "\\[abc",
             "\\[abc\\]",
             "123",
-            "*\\{",
+            "\\*\\{",
             "&#$",
-            "ab-*",
-            "...",
+            "ab-\\*",
+            "\\?\\+",
+            "\\.\\.\\.",
             "$^",
             "a2z#@",
             "",
@@ -380,7 +953,8 @@ mod tests {
     fn can_parse_as_literal_invalid() {
         for s in [
             "(abc)", "\\(abc)", "(abc\\)", "abc)", "(abc", "[123]", "\\[123]", "[123\\]",
-            "abc(123)", "(123)abc", ")(", "\\",
+            "abc(123)", "(123)abc", ")(", "\\", "abc*", "a?", "+", "a{2}", ".", "a.b", "\\d",
+            "a\\s",
         ] {
             assert!(parse_as_literal_kind(s).is_err());
         }
@@ -404,6 +978,14 @@ mod tests {
             "(a|2|$|%|^){100}",
             "(12|a|-)",
             "(12|a|-){100}",
+            "(a|b)?",
+            "(a|b){2,5}",
+            "(a|b){3,}",
+            "((abc))",
+            "((foo|bar)[0-9]{2}|baz)",
+            "(user[0-9]{3}|admin)",
+            "(a|)",
+            "(a(b(c|d)*)?|e+)",
         ] {
             assert!(matches!(
                 parse_as_parentheses_kind(s).unwrap().kind,
@@ -421,7 +1003,6 @@ mod tests {
             "abc)",
             "(abc))",
             "((abc)",
-            "((abc))",
             "|abc)",
             "(abc|",
             "(abc)a",
@@ -429,6 +1010,9 @@ mod tests {
             "(abc){1}}",
             "{1}(abc)",
             "(abc){{1}",
+            "(abc)(def)",
+            "(a|[b)",
+            "(a|b(c)",
         ] {
             assert!(parse_as_parentheses_kind(s).is_err())
         }
@@ -445,6 +1029,20 @@ mod tests {
             "[A-Za-z0-9]{3}",
             "[a&^#]",
             "[\\|]",
+            "[abc]*",
+            "[abc]+",
+            "[abc]{3,16}",
+            "[*+?]",
+            "[^aeiou]",
+            "[^a-z]{3}",
+            "[^\\^]",
+            "[\\^a]",
+            "[^]",
+            "[[:alpha:]]",
+            "[[:digit:]a-f]{4}",
+            "[^[:space:][:punct:]]",
+            "[_[:alnum:]-]+",
+            "[:[:upper:]:]",
         ] {
             assert!(parse_as_brackets_kind(s).is_ok())
         }
@@ -453,8 +1051,22 @@ mod tests {
     #[test]
     fn can_parse_as_brackets_invalid() {
         for s in [
-            "[abc\\]", "\\[abc]", "[()]", "[[]]", "[(]", "[)]", "[[]", "[]]", "[abc|]", "[|]",
-            "[abc}]", "[{abc]",
+            "[abc\\]",
+            "\\[abc]",
+            "[()]",
+            "[[]]",
+            "[(]",
+            "[)]",
+            "[[]",
+            "[]]",
+            "[abc|]",
+            "[|]",
+            "[abc}]",
+            "[{abc]",
+            "[[:alphabet:]]",
+            "[[:alpha]]",
+            "[[:]]",
+            "[[::]]",
         ] {
             assert!(parse_as_brackets_kind(s).is_err())
         }
@@ -468,7 +1080,7 @@ mod tests {
             "abc",
             "a2c",
             "ABC",
-            "...",
+            "\\.\\.\\.",
             "$^",
             "#$@#",
             "abc\\(",
@@ -484,7 +1096,7 @@ mod tests {
                 subpatterns: vec![SubPattern {
                     value: String::from(*value),
                     kind: SubPatternKind::Literal,
-                    quantifier: 1,
+                    quantifier: Quantifier::exactly(1),
                 }],
             };
             assert_eq!(actual, expected);
@@ -501,9 +1113,13 @@ mod tests {
                 subpatterns: vec![SubPattern {
                     value: String::from(&value[1..(value.len() - 1)]),
                     kind: SubPatternKind::Parentheses {
-                        pipe_positions: Some(vec![1, 3]),
+                        alternatives: value[1..(value.len() - 1)]
+                            .split('|')
+                            .map(literal_pattern)
+                            .collect(),
+                        weights: vec![1; 3],
                     },
-                    quantifier: 1,
+                    quantifier: Quantifier::exactly(1),
                 }],
             };
             assert_eq!(actual, expected);
@@ -517,9 +1133,14 @@ mod tests {
             subpatterns: vec![SubPattern {
                 value: String::from("a|b|c"),
                 kind: SubPatternKind::Parentheses {
-                    pipe_positions: Some(vec![1, 3]),
+                    alternatives: vec![
+                        literal_pattern("a"),
+                        literal_pattern("b"),
+                        literal_pattern("c"),
+                    ],
+                    weights: vec![1; 3],
                 },
-                quantifier: 5,
+                quantifier: Quantifier::exactly(5),
             }],
         };
         assert_eq!(actual, expected);
@@ -532,9 +1153,48 @@ mod tests {
             subpatterns: vec![SubPattern {
                 value: String::from("a\\)bc"),
                 kind: SubPatternKind::Parentheses {
-                    pipe_positions: None,
+                    alternatives: vec![literal_pattern("a\\)bc")],
+                    weights: vec![1],
                 },
-                quantifier: 23,
+                quantifier: Quantifier::exactly(23),
+            }],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parse_valid_nested_parentheses_pattern() {
+        let actual = Pattern::parse("((a|b)c|d)").unwrap();
+        let expected = Pattern {
+            subpatterns: vec![SubPattern {
+                value: String::from("(a|b)c|d"),
+                kind: SubPatternKind::Parentheses {
+                    alternatives: vec![
+                        Pattern {
+                            subpatterns: vec![
+                                SubPattern {
+                                    value: String::from("a|b"),
+                                    kind: SubPatternKind::Parentheses {
+                                        alternatives: vec![
+                                            literal_pattern("a"),
+                                            literal_pattern("b"),
+                                        ],
+                                        weights: vec![1; 2],
+                                    },
+                                    quantifier: Quantifier::exactly(1),
+                                },
+                                SubPattern {
+                                    value: String::from("c"),
+                                    kind: SubPatternKind::Literal,
+                                    quantifier: Quantifier::exactly(1),
+                                },
+                            ],
+                        },
+                        literal_pattern("d"),
+                    ],
+                    weights: vec![1; 2],
+                },
+                quantifier: Quantifier::exactly(1),
             }],
         };
         assert_eq!(actual, expected);
@@ -548,12 +1208,12 @@ mod tests {
                 SubPattern {
                     value: String::from("abc"),
                     kind: SubPatternKind::Literal,
-                    quantifier: 1,
+                    quantifier: Quantifier::exactly(1),
                 },
                 SubPattern {
                     value: String::from("123"),
-                    kind: SubPatternKind::Brackets,
-                    quantifier: 1,
+                    kind: SubPatternKind::Brackets { negated: false },
+                    quantifier: Quantifier::exactly(1),
                 },
             ],
         };
@@ -573,6 +1233,29 @@ mod tests {
             "xyz(a|b|c\\))123",
             "[5-9\\]]{5}abc",
             "[\\[5-9]{5}abc",
+            "[a-z]{3,16}",
+            "user[0-9]{2,}",
+            "abc?",
+            "a*b+c?",
+            "(Mr|Ms)\\.? [A-Z][a-z]+",
+            "\\*+",
+            "\\d{3}-\\d{4}",
+            "[\\w.]+@\\w+\\.com",
+            ".*",
+            "\\S\\s\\W\\D",
+            "[^\\d\\s]",
+            "a\\tb\\n",
+            "\\x41{3}",
+            "\\u{1F600}+",
+            "[\\u{0400}-\\u{04FF}]{5}",
+            "(\\x{41}|\\u0042)",
+            "[^\\x00-\\x1f]",
+            "\\p{Greek}{3}",
+            "\\pL\\PL",
+            "[\\p{Lu}\\p{Nd}_]+",
+            "[\\u{4E00}-\\u{9FFF}]{4}",
+            "[[:alpha:]]{3}-[[:digit:][:upper:]]+",
+            "([[:lower:]]|[^[:print:]])",
         ] {
             assert!(Pattern::parse(input).is_ok());
         }
@@ -597,6 +1280,22 @@ mod tests {
             "abc[",
             "[abc[",
             "]abc]",
+            "*abc",
+            "abc**",
+            "[a-z]{3,1}",
+            "[a-z]{3",
+            "(a|b)+?",
+            "\\x4",
+            "\\xZZ",
+            "\\u{}",
+            "\\u{110000}",
+            "[\\u{D800}]",
+            "abc\\",
+            "[z-a]",
+            "\\p{NotAProperty}",
+            "[\\p{Greek]",
+            "\\p",
+            "\\p{Greek",
         ]
         .iter()
         {
@@ -604,6 +1303,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_errors_report_kind_and_offset() {
+        for (value, kind, offset) in [
+            ("(abc", ParseErrorKind::UnbalancedParenthesis, 0),
+            ("abc)", ParseErrorKind::UnbalancedParenthesis, 3),
+            ("x(a|(b)", ParseErrorKind::UnbalancedParenthesis, 1),
+            ("ab[cd", ParseErrorKind::UnbalancedBracket, 2),
+            ("abc]", ParseErrorKind::UnbalancedBracket, 3),
+            ("[a[b]", ParseErrorKind::UnbalancedBracket, 2),
+            ("*abc", ParseErrorKind::InvalidQuantifier, 0),
+            ("abc**", ParseErrorKind::InvalidQuantifier, 4),
+            ("[a-z]{3,1}", ParseErrorKind::InvalidQuantifier, 5),
+            ("(ab){3", ParseErrorKind::InvalidQuantifier, 4),
+            ("a{x}", ParseErrorKind::InvalidQuantifier, 1),
+            ("a[0-9z-a]", ParseErrorKind::InvalidRange, 5),
+            ("[^\\d9-0]", ParseErrorKind::InvalidRange, 4),
+            ("(a|[\\u{9FFF}-\\u{4E00}])", ParseErrorKind::InvalidRange, 4),
+            ("ab\\xZZ", ParseErrorKind::InvalidEscape, 2),
+            ("[\\u{D800}]", ParseErrorKind::InvalidEscape, 1),
+            ("\\u{D800}", ParseErrorKind::InvalidEscape, 0),
+            ("a\\u{}", ParseErrorKind::InvalidEscape, 1),
+            ("ab\\u{1F600", ParseErrorKind::InvalidEscape, 2),
+            ("[a\\x{110000}]", ParseErrorKind::InvalidEscape, 2),
+            ("abc\\", ParseErrorKind::TrailingEscape, 3),
+            ("x\\p{Foo}", ParseErrorKind::UnknownClass, 1),
+            ("[a[:alphabet:]]", ParseErrorKind::UnknownClass, 2),
+            ("a|b", ParseErrorKind::UnescapedCharacter, 1),
+            ("日本(語", ParseErrorKind::UnbalancedParenthesis, 2),
+            ("é[ß-a]", ParseErrorKind::InvalidRange, 2),
+            ("(a:0|b:0)", ParseErrorKind::InvalidWeight, 0),
+            ("x(a:1|b:99999999999)", ParseErrorKind::InvalidWeight, 8),
+        ] {
+            assert_eq!(
+                Pattern::parse(value).unwrap_err(),
+                ParseError::new(kind, offset),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn parse_sampler_checks_the_sampler() {
+        let universe = CharClass::printable_ascii();
+        assert!(Pattern::parse_sampler("[a-z]{3}", &universe).is_ok());
+        assert_eq!(
+            Pattern::parse_sampler("a{x}", &universe),
+            Err(SamplerError::Parse(ParseError::new(
+                ParseErrorKind::InvalidQuantifier,
+                1
+            )))
+        );
+        assert_eq!(
+            Pattern::parse_sampler("[^\\x00-\\u{10FFFF}]", &universe),
+            Err(SamplerError::EmptyClass)
+        );
+        assert_eq!(
+            Pattern::parse_sampler("a{1048577}", &universe),
+            Err(SamplerError::TooLong)
+        );
+    }
+
+    #[test]
+    fn check_annotate() {
+        let err = Pattern::parse("日本[a-").unwrap_err();
+        assert_eq!(
+            err.annotate("日本[a-"),
+            "    日本[a-\n      ^\nerror: unbalanced bracket"
+        );
+    }
+
     #[test]
     fn check_special_characters() {
         for c in "()[]{}\\|".chars() {
@@ -621,16 +1391,37 @@ mod tests {
     #[test]
     fn check_pop_quantifier() {
         for (input, expected) in [
-            ("(abc){5}", ("(abc)", Some(5))),
-            ("[abc]{25}", ("[abc]", Some(25))),
-            ("[123]{25}", ("[123]", Some(25))),
-            ("[123]{00025}", ("[123]", Some(25))),
+            ("(abc){5}", ("(abc)", Some(Quantifier::exactly(5)))),
+            ("[abc]{25}", ("[abc]", Some(Quantifier::exactly(25)))),
+            ("[123]{25}", ("[123]", Some(Quantifier::exactly(25)))),
+            ("[123]{00025}", ("[123]", Some(Quantifier::exactly(25)))),
             ("[abc]\\{123}", ("[abc]\\{123}", None)),
             ("[abc]{123\\}", ("[abc]{123\\}", None)),
             ("[abc]\\{123\\}", ("[abc]\\{123\\}", None)),
             ("[abc]\\{}", ("[abc]\\{}", None)),
             ("[abc]{\\}", ("[abc]{\\}", None)),
             ("[abc]", ("[abc]", None)),
+            ("[abc]{2,5}", ("[abc]", Some(Quantifier { min: 2, max: 5 }))),
+            ("[abc]{2,}", ("[abc]", Some(Quantifier::at_least(2)))),
+            ("[abc]{0,0}", ("[abc]", Some(Quantifier::exactly(0)))),
+            ("[abc]{1000}", ("[abc]", Some(Quantifier::exactly(1000)))),
+            (
+                "[abc]{4294967295}",
+                ("[abc]", Some(Quantifier::exactly(u32::MAX))),
+            ),
+            ("[abc]{4294967296}", ("[abc]{4294967296}", None)),
+            (
+                "[abc]{4294967295,}",
+                ("[abc]", Some(Quantifier::at_least(u32::MAX))),
+            ),
+            ("[abc]?", ("[abc]", Some(Quantifier { min: 0, max: 1 }))),
+            ("[abc]*", ("[abc]", Some(Quantifier::at_least(0)))),
+            ("(abc)+", ("(abc)", Some(Quantifier::at_least(1)))),
+            ("[abc]{5,2}", ("[abc]{5,2}", None)),
+            ("[abc]{,2}", ("[abc]{,2}", None)),
+            ("[abc]{1,2,3}", ("[abc]{1,2,3}", None)),
+            ("[abc]\\*", ("[abc]\\*", None)),
+            ("[abc]\\\\*", ("[abc]\\\\", Some(Quantifier::at_least(0)))),
         ]
         .iter()
         {
@@ -644,6 +1435,9 @@ mod tests {
             ("(abc)", vec![0, 4]),
             ("(a|b|c)", vec![0, 2, 4, 6]),
             ("(a|bbb|c)", vec![0, 2, 6, 8]),
+            ("((a|b)|c)", vec![0, 6, 8]),
+            ("(a|(b|c)d)", vec![0, 2, 9]),
+            ("(a\\(|b)", vec![0, 4, 6]),
         ] {
             assert_eq!(find_parentheses_boundaries(input).unwrap(), expected);
         }
@@ -651,14 +1445,14 @@ mod tests {
 
     #[test]
     fn check_find_parentheses_boundaries_invalid() {
-        for invalid in ["abc", "(abc", "abc)", "[a|b|c]"] {
+        for invalid in ["abc", "(abc", "abc)", "[a|b|c]", "(a)(b)", "((a)"] {
             assert!(find_parentheses_boundaries(invalid).is_err())
         }
     }
 
     #[test]
     fn check_expand_ranges() {
-        let mut actual: String;
+        let mut actual: CharClass;
         for (input, expected) in [
             ("A-Z", "ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
             ("M-Z", "MNOPQRSTUVWXYZ"),
@@ -675,14 +1469,56 @@ mod tests {
             ("abc0-9", "abc0123456789"),
             ("A-Z123", "ABCDEFGHIJKLMNOPQRSTUVWXYZ123"),
             ("123A-Z", "123ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
-            ("*-^0-9", "*-^0123456789"),
-            ("*-^---0-9", "*-^---0123456789"),
-            ("*-^---a-f", "*-^---abcdef"),
-            ("*-^---a-f7-9", "*-^---abcdef789"),
-            ("*-^---a-f7-9--abc--", "*-^---abcdef789--abc--"),
+            ("-a-c-", "-abc"),
+            ("\\--/", "-./"),
+            ("!-#", "!\"#"),
+            ("\\x41-\\x43", "ABC"),
+            ("\\u{3B1}-\\u{3B3}", "αβγ"),
+            ("α-γ", "αβγ"),
         ] {
-            actual = expand_ranges(input);
-            assert_eq!(actual, expected);
+            actual = expand_ranges(input).unwrap();
+            assert_eq!(actual, expected.chars().collect());
+        }
+        for (input, expected) in [
+            ("*-^---0-9", vec![('*', '^'), ('-', '-')]),
+            ("\\u{4E00}-\\u{9FFF}", vec![('\u{4E00}', '\u{9FFF}')]),
+            ("\\x00-\\x1f~", vec![('\0', '\x1f'), ('~', '~')]),
+        ] {
+            actual = expand_ranges(input).unwrap();
+            assert_eq!(actual, CharClass::from_ranges(expected));
+        }
+        for input in ["z-a", "9-0", "\\u{9FFF}-\\u{4E00}"] {
+            assert!(expand_ranges(input).is_err());
+        }
+    }
+
+    #[test]
+    fn property_classes_sample_expected_characters() {
+        let universe = CharClass::printable_ascii();
+        for (pattern, is_expected) in [
+            ("\\p{Greek}", (|c| !c.is_ascii()) as fn(char) -> bool),
+            ("\\pL", |c: char| c.is_alphabetic()),
+            ("\\p{Lu}", |c: char| c.is_uppercase()),
+            ("[\\p{Nd}a]", |c: char| c.is_numeric() || c == 'a'),
+            ("\\P{L}", |c: char| {
+                !c.is_alphabetic() && (' '..='~').contains(&c)
+            }),
+            ("[^\\P{L}]", |c: char| c.is_ascii_alphabetic()),
+            ("\\p{Emoji}", |c: char| {
+                !c.is_ascii() || "#*0123456789".contains(c)
+            }),
+            ("[\\u{4E00}-\\u{9FFF}]", |c| {
+                ('\u{4E00}'..='\u{9FFF}').contains(&c)
+            }),
+        ] {
+            let sampler = Pattern::parse(pattern)
+                .unwrap()
+                .to_string_sampler(&universe);
+            for _ in 0..100 {
+                let result = sampler.sample(&mut thread_rng());
+                assert_eq!(result.chars().count(), 1, "{}", pattern);
+                assert!(is_expected(result.chars().next().unwrap()), "{}", pattern);
+            }
         }
     }
 
@@ -696,11 +1532,65 @@ mod tests {
             ("\\(abc\\]", "(abc]"),
             ("\\[abc\\]", "[abc]"),
             ("\\\\", "\\"),
+            ("a\\tb\\nc\\r", "a\tb\nc\r"),
+            ("\\f\\v\\0", "\x0C\x0B\0"),
+            ("\\x41\\x7e", "A~"),
+            ("\\x{41}\\x{1F600}", "A\u{1F600}"),
+            ("\\u00e9\\u{1F600}", "\u{e9}\u{1F600}"),
+            ("\\u{41}{2}", "A{2}"),
         ] {
             assert_eq!(unescape(input), expected);
         }
     }
 
+    #[test]
+    fn check_parse_escape() {
+        for (input, expected) in [
+            ("\\n", Some(('\n', 2))),
+            ("\\(", Some(('(', 2))),
+            ("\\x41z", Some(('A', 4))),
+            ("\\u0041", Some(('A', 6))),
+            ("\\u{1F600}}", Some(('\u{1F600}', 9))),
+            ("\\", None),
+            ("\\x4", None),
+            ("\\xZZ", None),
+            ("\\u{}", None),
+            ("\\u{1F600", None),
+            ("\\u{0000041}", None),
+            ("\\u{110000}", None),
+            ("\\u{D800}", None),
+            ("n", None),
+        ] {
+            assert_eq!(parse_escape(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn escape_sequences_sample_expected_characters() {
+        let universe = CharClass::printable_ascii();
+        for (pattern, expected) in [
+            ("\\t\\x41\\u{1F600}", vec!["\tA\u{1F600}"]),
+            ("(a\\nb|\\u00e9)", vec!["a\nb", "\u{e9}"]),
+            ("\\u{1F600}{2}", vec!["\u{1F600}\u{1F600}"]),
+            ("[\\x41-\\x43]", vec!["A", "B", "C"]),
+            (
+                "[\\u{0430}-\\u{0432}]",
+                vec!["\u{430}", "\u{431}", "\u{432}"],
+            ),
+        ] {
+            let sampler = Pattern::parse(pattern)
+                .unwrap()
+                .to_string_sampler(&universe);
+            for _ in 0..100 {
+                assert!(
+                    expected.contains(&sampler.sample(&mut thread_rng()).as_str()),
+                    "{}",
+                    pattern
+                );
+            }
+        }
+    }
+
     #[test]
     fn check_seek_to_unescaped() {
         for (input, target, expected) in [
@@ -716,19 +1606,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn check_split_at_positions() {
-        let mut actual: Vec<String>;
-        let mut expected: Vec<String>;
-        actual = split_at_positions("abc", &[1]);
-        expected = vec![String::from("a"), String::from("c")];
-        assert_eq!(actual, expected);
-
-        actual = split_at_positions("abc", &[]);
-        expected = vec![String::from("abc")];
-        assert_eq!(actual, expected);
-    }
-
     #[test]
     fn check_pop_subpattern_parentheses() {
         let actual = pop_subpattern("(abc)").unwrap();
@@ -736,9 +1613,10 @@ mod tests {
             SubPattern {
                 value: String::from("abc"),
                 kind: SubPatternKind::Parentheses {
-                    pipe_positions: None,
+                    alternatives: vec![literal_pattern("abc")],
+                    weights: vec![1],
                 },
-                quantifier: 1,
+                quantifier: Quantifier::exactly(1),
             },
             4,
         );
@@ -752,9 +1630,10 @@ mod tests {
             SubPattern {
                 value: String::from("abc"),
                 kind: SubPatternKind::Parentheses {
-                    pipe_positions: None,
+                    alternatives: vec![literal_pattern("abc")],
+                    weights: vec![1],
                 },
-                quantifier: 5,
+                quantifier: Quantifier::exactly(5),
             },
             7,
         );
@@ -768,19 +1647,77 @@ mod tests {
             SubPattern {
                 value: String::from("abc"),
                 kind: SubPatternKind::Parentheses {
-                    pipe_positions: None,
+                    alternatives: vec![literal_pattern("abc")],
+                    weights: vec![1],
                 },
-                quantifier: 1,
+                quantifier: Quantifier::exactly(1),
             },
             4,
         );
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn parse_weighted_parentheses_pattern() {
+        for (value, alternatives, weights) in [
+            (
+                "(200:90|404:8|500:2)",
+                vec!["200", "404", "500"],
+                vec![90, 8, 2],
+            ),
+            ("(a:3|b:0)", vec!["a", "b"], vec![3, 0]),
+            ("(a:1|b)", vec!["a:1", "b"], vec![1, 1]),
+            ("(a:b|c:)", vec!["a:b", "c:"], vec![1, 1]),
+            ("(a\\:3|b:0)", vec!["a\\:3", "b:0"], vec![1, 1]),
+            ("(12\\:30|13\\:45)", vec!["12\\:30", "13\\:45"], vec![1, 1]),
+            ("(1:2:3|4:5)", vec!["1:2", "4"], vec![3, 5]),
+            ("(1:2:3)", vec!["1:2:3"], vec![1]),
+        ] {
+            let actual = Pattern::parse(value).unwrap();
+            let expected = Pattern {
+                subpatterns: vec![SubPattern {
+                    value: String::from(&value[1..(value.len() - 1)]),
+                    kind: SubPatternKind::Parentheses {
+                        alternatives: alternatives.into_iter().map(literal_pattern).collect(),
+                        weights,
+                    },
+                    quantifier: Quantifier::exactly(1),
+                }],
+            };
+            assert_eq!(actual, expected, "{}", value);
+        }
+        for (value, expected) in [
+            ("([[:digit:]]:5|x:1)", vec![5, 1]),
+            ("(x{2}:4|(a:1|b:7):1)", vec![4, 1]),
+            ("([:]:2|x:1)", vec![2, 1]),
+            ("([a:3])", vec![1]),
+        ] {
+            match &Pattern::parse(value).unwrap().subpatterns[0].kind {
+                SubPatternKind::Parentheses { weights, .. } => assert_eq!(weights, &expected),
+                _ => panic!("{}", value),
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_alternatives_sample_in_proportion() {
+        let sampler = Pattern::parse("(200:90|404:8|500:2|503:0)")
+            .unwrap()
+            .to_string_sampler(&CharClass::printable_ascii());
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..10000 {
+            *counts.entry(sampler.sample(&mut thread_rng())).or_insert(0) += 1;
+        }
+        assert!((8700..9300).contains(&counts["200"]));
+        assert!((600..1000).contains(&counts["404"]));
+        assert!((100..300).contains(&counts["500"]));
+        assert!(!counts.contains_key("503"));
+    }
+
     #[test]
     fn check_pop_subpattern_invalid() {
         for input in ["(abc){5", "(abc){z}", "abc]"] {
-            assert!(pop_subpattern(input).is_none());
+            assert!(pop_subpattern(input).is_err());
         }
     }
 
@@ -790,8 +1727,8 @@ mod tests {
         let expected = (
             SubPattern {
                 value: String::from("abc"),
-                kind: SubPatternKind::Brackets,
-                quantifier: 1,
+                kind: SubPatternKind::Brackets { negated: false },
+                quantifier: Quantifier::exactly(1),
             },
             4,
         );
@@ -804,8 +1741,8 @@ mod tests {
         let expected = (
             SubPattern {
                 value: String::from("abc"),
-                kind: SubPatternKind::Brackets,
-                quantifier: 15,
+                kind: SubPatternKind::Brackets { negated: false },
+                quantifier: Quantifier::exactly(15),
             },
             8,
         );
@@ -818,14 +1755,208 @@ mod tests {
         let expected = (
             SubPattern {
                 value: String::from("abc"),
-                kind: SubPatternKind::Brackets,
-                quantifier: 15,
+                kind: SubPatternKind::Brackets { negated: false },
+                quantifier: Quantifier::exactly(15),
             },
             8,
         );
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn check_pop_subpattern_negated_brackets() {
+        let actual = pop_subpattern("[^a-c]{2}").unwrap();
+        let expected = (
+            SubPattern {
+                value: String::from("a-c"),
+                kind: SubPatternKind::Brackets { negated: true },
+                quantifier: Quantifier::exactly(2),
+            },
+            8,
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn negated_brackets_use_universe() {
+        let pattern = Pattern::parse("[^a-y]").unwrap();
+        let universe = CharClass::from_range('a', 'z');
+        for _ in 0..10 {
+            assert_eq!(
+                pattern
+                    .to_string_sampler(&universe)
+                    .sample(&mut thread_rng()),
+                "z"
+            );
+        }
+        let pattern = Pattern::parse("[^\\^]").unwrap();
+        let sampler = pattern.to_string_sampler(&CharClass::printable_ascii());
+        for _ in 0..100 {
+            let result = sampler.sample(&mut thread_rng());
+            assert!(result != "^" && (' '..='~').contains(&result.chars().next().unwrap()));
+        }
+    }
+
+    #[test]
+    fn check_pop_subpattern_shorthand() {
+        for (input, expected) in [
+            (
+                "\\d{3}abc",
+                (
+                    SubPattern {
+                        value: String::from("\\d"),
+                        kind: SubPatternKind::Brackets { negated: false },
+                        quantifier: Quantifier::exactly(3),
+                    },
+                    4,
+                ),
+            ),
+            (
+                ".+abc",
+                (
+                    SubPattern {
+                        value: String::from("\n"),
+                        kind: SubPatternKind::Brackets { negated: true },
+                        quantifier: Quantifier::at_least(1),
+                    },
+                    1,
+                ),
+            ),
+            (
+                "ab\\W",
+                (
+                    SubPattern {
+                        value: String::from("ab"),
+                        kind: SubPatternKind::Literal,
+                        quantifier: Quantifier::exactly(1),
+                    },
+                    1,
+                ),
+            ),
+            (
+                "a\\\\d",
+                (
+                    SubPattern {
+                        value: String::from("a\\\\d"),
+                        kind: SubPatternKind::Literal,
+                        quantifier: Quantifier::exactly(1),
+                    },
+                    3,
+                ),
+            ),
+        ] {
+            assert_eq!(pop_subpattern(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn shorthand_classes_sample_expected_characters() {
+        let universe = CharClass::printable_ascii();
+        for (pattern, expected) in [
+            ("\\d", "0123456789"),
+            ("[\\da-c]", "0123456789abc"),
+            ("[^\\W_]", "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+            ("[\\D\\d]", " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~"),
+            ("\\s", " \t\n\r\x0B\x0C"),
+            ("[.]", "."),
+        ] {
+            let sampler = Pattern::parse(pattern).unwrap().to_string_sampler(&universe);
+            for _ in 0..100 {
+                assert!(expected.contains(&sampler.sample(&mut thread_rng())));
+            }
+        }
+        let sampler = Pattern::parse(".")
+            .unwrap()
+            .to_string_sampler(&CharClass::from_range('\t', '\r'));
+        for _ in 0..100 {
+            assert_ne!(sampler.sample(&mut thread_rng()), "\n");
+        }
+    }
+
+    #[test]
+    fn posix_classes_sample_expected_characters() {
+        let universe = CharClass::printable_ascii();
+        for (pattern, expected) in [
+            ("[[:digit:]]", "0123456789"),
+            ("[[:xdigit:]]", "0123456789ABCDEFabcdef"),
+            ("[[:upper:]a]", "ABCDEFGHIJKLMNOPQRSTUVWXYZa"),
+            (
+                "[^[:alnum:][:space:]]",
+                "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~",
+            ),
+            ("[[:punct:]]", "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~"),
+            ("[[:blank:]]", " \t"),
+            ("[x-z[:lower:]]", "abcdefghijklmnopqrstuvwxyz"),
+        ] {
+            let sampler = Pattern::parse(pattern)
+                .unwrap()
+                .to_string_sampler(&universe);
+            for _ in 0..100 {
+                assert!(
+                    expected.contains(&sampler.sample(&mut thread_rng())),
+                    "{}",
+                    pattern
+                );
+            }
+        }
+        assert_eq!(
+            bracket_class("[:print:]", &universe).unwrap(),
+            CharClass::printable_ascii()
+        );
+    }
+
+    #[test]
+    fn check_seek_to_shorthand() {
+        for (input, expected) in [
+            ("\\d", 0),
+            ("ab\\S", 2),
+            ("ab\\\\S", 5),
+            ("\\\\\\w", 2),
+            ("abc", 3),
+            ("a\\x", 3),
+        ] {
+            assert_eq!(seek_to_shorthand(input), expected);
+        }
+    }
+
+    #[test]
+    fn multibyte_patterns_sample_expected_strings() {
+        let universe = CharClass::printable_ascii();
+        for (pattern, expected) in [
+            ("(café|naïve)", vec!["café", "naïve"]),
+            ("[αβγ]", vec!["α", "β", "γ"]),
+            ("é{2}ü?", vec!["éé", "ééü"]),
+            ("[α-γ]", vec!["α", "β", "γ"]),
+            ("日本[語ご]", vec!["日本語", "日本ご"]),
+            ("😀(\\😀|ß)", vec!["😀😀", "😀ß"]),
+        ] {
+            let sampler = Pattern::parse(pattern)
+                .unwrap()
+                .to_string_sampler(&universe);
+            for _ in 0..100 {
+                assert!(
+                    expected.contains(&sampler.sample(&mut thread_rng()).as_str()),
+                    "{}",
+                    pattern
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn check_pop_subpattern_multibyte() {
+        let actual = pop_subpattern("ab€{2}").unwrap();
+        assert_eq!(actual.0.value, "ab");
+        assert_eq!(actual.1, 1);
+        let actual = pop_subpattern("€{2}x").unwrap();
+        assert_eq!(actual.0.value, "€");
+        assert_eq!(actual.0.quantifier, Quantifier::exactly(2));
+        assert_eq!(actual.1, 5);
+        let actual = pop_subpattern("[é]ü").unwrap();
+        assert_eq!(actual.0.value, "é");
+        assert_eq!(actual.1, 3);
+    }
+
     #[test]
     fn check_pop_subpattern_literal() {
         let actual = pop_subpattern("abc123").unwrap();
@@ -833,13 +1964,49 @@ mod tests {
             SubPattern {
                 value: String::from("abc123"),
                 kind: SubPatternKind::Literal,
-                quantifier: 1,
+                quantifier: Quantifier::exactly(1),
             },
             5,
         );
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn check_pop_subpattern_literal_with_quantifier() {
+        let actual = pop_subpattern("abc{2,4}").unwrap();
+        let expected = (
+            SubPattern {
+                value: String::from("ab"),
+                kind: SubPatternKind::Literal,
+                quantifier: Quantifier::exactly(1),
+            },
+            1,
+        );
+        assert_eq!(actual, expected);
+
+        let actual = pop_subpattern("c{2,4}x").unwrap();
+        let expected = (
+            SubPattern {
+                value: String::from("c"),
+                kind: SubPatternKind::Literal,
+                quantifier: Quantifier { min: 2, max: 4 },
+            },
+            5,
+        );
+        assert_eq!(actual, expected);
+
+        let actual = pop_subpattern("\\.?").unwrap();
+        let expected = (
+            SubPattern {
+                value: String::from("\\."),
+                kind: SubPatternKind::Literal,
+                quantifier: Quantifier { min: 0, max: 1 },
+            },
+            2,
+        );
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn check_pop_subpattern_literal_with_another() {
         let actual = pop_subpattern("abc123(a|b|c)").unwrap();
@@ -847,10 +2014,109 @@ mod tests {
             SubPattern {
                 value: String::from("abc123"),
                 kind: SubPatternKind::Literal,
-                quantifier: 1,
+                quantifier: Quantifier::exactly(1),
             },
             5,
         );
         assert_eq!(actual, expected);
     }
+
+    /// Return the input string with a backslash before every character that
+    /// has a special meaning somewhere in a pattern.
+    fn escape(string: &str) -> String {
+        let mut result = String::from("");
+        for c in string.chars() {
+            if is_special_character(c)
+                || is_quantifier_character(c)
+                || is_wildcard_character(c)
+                || "^-:".contains(c)
+            {
+                result.push('\\');
+            }
+            result.push(c);
+        }
+        result
+    }
+
+    proptest! {
+        #[test]
+        fn parse_handles_arbitrary_unicode(string in any::<String>()) {
+            if let Ok(pattern) = Pattern::parse(&string) {
+                let sampler = pattern.to_string_sampler(&CharClass::unicode());
+                if sampler.max_length() < 1000 && !sampler.has_empty_support() {
+                    sampler.sample(&mut thread_rng());
+                }
+            }
+        }
+
+        #[test]
+        fn parse_handles_arbitrary_metacharacters(
+            string in "[(){}\\[\\]|\\\\?*+.^,\\-0-9a-zA-Zéß日本😀xu]{0,24}"
+        ) {
+            match Pattern::parse(&string) {
+                Ok(pattern) => {
+                    let sampler = pattern.to_string_sampler(&CharClass::unicode());
+                    if sampler.max_length() < 1000 && !sampler.has_empty_support() {
+                        sampler.sample(&mut thread_rng());
+                    }
+                }
+                Err(err) => prop_assert!(err.offset < string.chars().count()),
+            }
+        }
+
+        #[test]
+        fn escaped_literal_reproduces_itself(string in any::<String>()) {
+            let pattern = Pattern::parse(&escape(&string)).unwrap();
+            let sampler = pattern.to_string_sampler(&CharClass::printable_ascii());
+            prop_assert_eq!(sampler.sample(&mut thread_rng()), string);
+        }
+
+        #[test]
+        fn brackets_sample_listed_characters(
+            chars in proptest::collection::vec(any::<char>(), 1..10)
+        ) {
+            let string = chars.iter().collect::<String>();
+            let pattern = Pattern::parse(&format!("[{}]{{5}}", escape(&string))).unwrap();
+            let sampler = pattern.to_string_sampler(&CharClass::printable_ascii());
+            for c in sampler.sample(&mut thread_rng()).chars() {
+                prop_assert!(chars.contains(&c));
+            }
+        }
+
+        #[test]
+        fn parentheses_sample_an_alternative(
+            alternatives in proptest::collection::vec(any::<String>(), 1..5)
+        ) {
+            let escaped = alternatives.iter().map(|a| escape(a)).collect::<Vec<_>>();
+            let pattern = Pattern::parse(&format!("({})", escaped.join("|"))).unwrap();
+            let sampler = pattern.to_string_sampler(&CharClass::printable_ascii());
+            prop_assert!(alternatives.contains(&sampler.sample(&mut thread_rng())));
+        }
+
+        #[test]
+        fn scanners_return_character_boundaries(string in any::<String>()) {
+            prop_assert!(string.is_char_boundary(seek_to_unescaped(&string, vec!['(', '|', '-'])));
+            prop_assert!(string.is_char_boundary(seek_to_shorthand(&string)));
+            prop_assert!(string.is_char_boundary(seek_to_last_character(&string)));
+            prop_assert!(string.is_char_boundary(seek_to_closing_parenthesis(&string)));
+            let (rest, _) = pop_quantifier(&string);
+            prop_assert!(string.starts_with(rest));
+            if let Ok(indexes) = find_parentheses_boundaries(&string) {
+                for idx in indexes {
+                    prop_assert!(string.is_char_boundary(idx));
+                }
+            }
+            let _ = expand_ranges(&string);
+            unescape(&string);
+        }
+
+        #[test]
+        fn ranges_expand_between_endpoints(start in any::<char>(), end in any::<char>()) {
+            let range = format!("{}-{}", escape(&start.to_string()), escape(&end.to_string()));
+            match expand_ranges(&range) {
+                Ok(class) => prop_assert_eq!(class, CharClass::from_range(start, end)),
+                Err(_) => prop_assert!(start > end),
+            }
+        }
+    }
 }
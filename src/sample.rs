@@ -1,60 +1,858 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader, Error};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::RangeInclusive;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use rand_distr::{
+    Beta, Binomial, Cauchy, ChiSquared, Distribution, Exp, Gamma, Geometric, Hypergeometric,
+    LogNormal, Normal, Poisson, StudentT, Triangular, Weibull, Zipf,
+};
+
+use crate::class::CharClass;
+
+/// Upper limit on the length, in characters, of strings that a
+/// StringSampler may produce.
+pub const MAX_STRING_LENGTH: u64 = 1 << 20;
+
+/// Number of draws a TruncatedFloat makes before giving up on finding a
+/// value within its bounds.
+pub const MAX_TRUNCATION_ATTEMPTS: u32 = 1_000_000;
+
+/// Number of duplicates in a row a StringSampler draws before giving up on
+/// finding another distinct string.
+pub const MAX_DUPLICATE_DRAWS: u32 = 1_000_000;
 
 #[derive(Debug, PartialEq)]
 pub struct StringSampler {
-    pub support: Vec<Vec<String>>,
-    pub repetitions: Vec<u8>,
+    pub support: Vec<Support>,
+    pub repetitions: Vec<RangeInclusive<u32>>,
+}
+
+/// The values from which one component of a StringSampler draws on each
+/// repetition, either a set of strings, a set of characters or a set of
+/// nested samplers chosen in proportion to their weights.
+#[derive(Debug, PartialEq)]
+pub enum Support {
+    Strings(Vec<String>),
+    Class(CharClass),
+    Alternatives {
+        samplers: Vec<StringSampler>,
+        weights: Vec<u32>,
+    },
+}
+
+impl Support {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        match self {
+            Support::Strings(strings) => strings[random_index(strings.len(), rng)].clone(),
+            Support::Class(class) => class
+                .nth(integer_given_bounds(0, class.len(), rng))
+                .unwrap()
+                .to_string(),
+            Support::Alternatives { samplers, weights } => {
+                samplers[weighted_index(weights, rng)].sample(rng)
+            }
+        }
+    }
+
+    fn max_cardinality(&self) -> u128 {
+        match self {
+            Support::Strings(strings) => strings.iter().collect::<HashSet<_>>().len() as u128,
+            Support::Class(class) => u128::from(class.len()),
+            Support::Alternatives { samplers, weights } => samplers
+                .iter()
+                .zip(weights)
+                .filter(|(_, &w)| w > 0)
+                .map(|(s, _)| s.max_cardinality())
+                .fold(0, u128::saturating_add),
+        }
+    }
+
+    fn max_length(&self) -> u64 {
+        let longest = match self {
+            Support::Strings(strings) => strings.iter().map(|s| s.chars().count() as u64).max(),
+            Support::Class(class) => Some(if class.is_empty() { 0 } else { 1 }),
+            Support::Alternatives { samplers, weights } => samplers
+                .iter()
+                .zip(weights)
+                .filter(|(_, &w)| w > 0)
+                .map(|(s, _)| s.max_length())
+                .max(),
+        };
+        longest.unwrap_or(0)
+    }
 }
 
 impl StringSampler {
-    pub fn sample(&self) -> String {
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
         if self.support.len() != self.repetitions.len() {
             panic!("Support and repetitions do not have the same length.");
         }
         let mut result = String::from("");
         for (support, repetitions) in self.support.iter().zip(&self.repetitions) {
-            for _ in 0..*repetitions {
-                let idx = (random_uniform() * (support.len() as f64)).floor() as usize;
-                result.push_str(&support[idx])
+            let count = integer_given_bounds(
+                u64::from(*repetitions.start()),
+                u64::from(*repetitions.end()) + 1,
+                rng,
+            );
+            for _ in 0..count {
+                result.push_str(&support.sample(rng))
             }
         }
         result
     }
+
+    /// Return true if some component of the sampler has nothing to draw from,
+    /// such as a negated character class covering its whole universe.
+    pub fn has_empty_support(&self) -> bool {
+        self.support.iter().any(|support| match support {
+            Support::Strings(strings) => strings.is_empty(),
+            Support::Class(class) => class.is_empty(),
+            Support::Alternatives { samplers, weights } => {
+                weights.iter().all(|&w| w == 0)
+                    || samplers
+                        .iter()
+                        .zip(weights)
+                        .any(|(s, &w)| w > 0 && s.has_empty_support())
+            }
+        })
+    }
+
+    /// Return `k` distinct strings, drawing until each new one turns up. If
+    /// MAX_DUPLICATE_DRAWS duplicates are drawn in a row then return None.
+    pub fn sample_distinct<R: Rng + ?Sized>(&self, k: usize, rng: &mut R) -> Option<Vec<String>> {
+        let mut seen = HashSet::new();
+        let mut result = vec![];
+        let mut duplicates = 0;
+        while result.len() < k {
+            let value = self.sample(rng);
+            if seen.contains(&value) {
+                duplicates += 1;
+                if duplicates == MAX_DUPLICATE_DRAWS {
+                    return None;
+                }
+                continue;
+            }
+            duplicates = 0;
+            seen.insert(value.clone());
+            result.push(value);
+        }
+        Some(result)
+    }
+
+    /// Return an upper bound on the number of distinct strings this sampler
+    /// can produce, saturating at `u128::MAX`. The bound is exact unless two
+    /// different sequences of choices spell the same string.
+    pub fn max_cardinality(&self) -> u128 {
+        self.support
+            .iter()
+            .zip(&self.repetitions)
+            .map(|(support, repetitions)| {
+                repetition_cardinality(support.max_cardinality(), repetitions)
+            })
+            .fold(1, u128::saturating_mul)
+    }
+
+    /// Return the length, in characters, of the longest string this
+    /// sampler can produce.
+    pub fn max_length(&self) -> u64 {
+        self.support
+            .iter()
+            .zip(&self.repetitions)
+            .map(|(support, repetitions)| {
+                support
+                    .max_length()
+                    .saturating_mul(u64::from(*repetitions.end()))
+            })
+            .fold(0, u64::saturating_add)
+    }
+}
+
+/// Return the number of sequences of `choices` possible values repeated any
+/// number of times in `repetitions`, saturating at `u128::MAX`.
+fn repetition_cardinality(choices: u128, repetitions: &RangeInclusive<u32>) -> u128 {
+    let (min, max) = (*repetitions.start(), *repetitions.end());
+    match choices {
+        0 => u128::from(min == 0),
+        1 => u128::from(max - min) + 1,
+        _ => {
+            let mut total: u128 = 0;
+            let mut power = choices.saturating_pow(min);
+            for _ in min..=max {
+                total = total.saturating_add(power);
+                if total == u128::MAX {
+                    break;
+                }
+                power = power.saturating_mul(choices);
+            }
+            total
+        }
+    }
+}
+
+/// A continuous distribution from which floating point numbers are drawn.
+/// Each constructor checks its parameters and returns a ParameterError
+/// naming the first one that is out of range.
+#[derive(Debug, Clone, Copy)]
+pub enum FloatDistribution {
+    Uniform { lower: f64, upper: f64 },
+    Normal(Normal<f64>),
+    LogNormal(LogNormal<f64>),
+    Exponential(Exp<f64>),
+    Gamma(Gamma<f64>),
+    Beta(Beta<f64>),
+    Cauchy(Cauchy<f64>),
+    StudentT(StudentT<f64>),
+    ChiSquared(ChiSquared<f64>),
+    Weibull(Weibull<f64>),
+    Triangular(Triangular<f64>),
+}
+
+/// A distribution parameter that lies outside the values it may take.
+#[derive(Debug, PartialEq)]
+pub struct ParameterError {
+    pub parameter: &'static str,
+    pub requirement: &'static str,
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} must be {}", self.parameter, self.requirement)
+    }
 }
 
-pub fn integer_given_bounds(lower: u64, upper: u64) -> u64 {
-    lower + (random_uniform() * ((upper - lower) as f64)).floor() as u64
+/// The reason a distribution could not be built from its name: the name is
+/// unknown, looking up a parameter failed, or a parameter is out of range.
+#[derive(Debug, PartialEq)]
+pub enum DistributionError<E> {
+    Unknown,
+    Lookup(E),
+    Invalid(ParameterError),
 }
 
-pub fn float_given_bounds(lower: f64, upper: f64) -> f64 {
-    lower + random_uniform() * (upper - lower)
+impl<E: fmt::Display> fmt::Display for DistributionError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DistributionError::Unknown => write!(f, "unknown distribution"),
+            DistributionError::Lookup(err) => write!(f, "{}", err),
+            DistributionError::Invalid(err) => write!(f, "{}", err),
+        }
+    }
 }
 
-pub fn from_wordlist(file_path: &str) -> Result<String, Error> {
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
-    let mut selected_word = vec![String::from("")];
+impl FloatDistribution {
+    /// Build the distribution with the given name, such as `normal`, reading
+    /// each parameter with `float`, which is given the default for a parameter
+    /// that may be left out. The uniform distribution reads `lower` and
+    /// `upper`, by default 0 and 1.
+    pub fn from_name<E, F>(name: &str, float: F) -> Result<FloatDistribution, DistributionError<E>>
+    where
+        F: Fn(&str, Option<f64>) -> Result<f64, E>,
+    {
+        let parameter = |name, default| float(name, default).map_err(DistributionError::Lookup);
+        match name {
+            "uniform" => FloatDistribution::uniform(
+                parameter("lower", Some(0.0))?,
+                parameter("upper", Some(1.0))?,
+            ),
+            "normal" => FloatDistribution::normal(
+                parameter("mean", Some(0.0))?,
+                parameter("sd", Some(1.0))?,
+            ),
+            "lognormal" => FloatDistribution::log_normal(
+                parameter("mean", Some(0.0))?,
+                parameter("sd", Some(1.0))?,
+            ),
+            "exponential" => FloatDistribution::exponential(parameter("rate", Some(1.0))?),
+            "gamma" => {
+                FloatDistribution::gamma(parameter("shape", None)?, parameter("scale", Some(1.0))?)
+            }
+            "beta" => FloatDistribution::beta(parameter("alpha", None)?, parameter("beta", None)?),
+            "cauchy" => FloatDistribution::cauchy(
+                parameter("location", Some(0.0))?,
+                parameter("scale", Some(1.0))?,
+            ),
+            "t" => FloatDistribution::student_t(parameter("df", None)?),
+            "chisquared" => FloatDistribution::chi_squared(parameter("df", None)?),
+            "weibull" => FloatDistribution::weibull(
+                parameter("scale", Some(1.0))?,
+                parameter("shape", None)?,
+            ),
+            "triangular" => {
+                let min = parameter("min", None)?;
+                let max = parameter("max", None)?;
+                FloatDistribution::triangular(min, max, parameter("mode", Some((min + max) / 2.0))?)
+            }
+            _ => return Err(DistributionError::Unknown),
+        }
+        .map_err(DistributionError::Invalid)
+    }
+
+    pub fn uniform(lower: f64, upper: f64) -> Result<FloatDistribution, ParameterError> {
+        check_finite("lower", lower)?;
+        check_finite("upper", upper)?;
+        if lower >= upper {
+            return Err(ParameterError {
+                parameter: "lower",
+                requirement: "strictly less than upper",
+            });
+        }
+        Ok(FloatDistribution::Uniform { lower, upper })
+    }
+
+    pub fn normal(mean: f64, sd: f64) -> Result<FloatDistribution, ParameterError> {
+        check_finite("mean", mean)?;
+        check_positive("sd", sd)?;
+        Ok(FloatDistribution::Normal(Normal::new(mean, sd).unwrap()))
+    }
+
+    /// The distribution of `exp(x)` where `x` is normal with the given mean
+    /// and standard deviation.
+    pub fn log_normal(mean: f64, sd: f64) -> Result<FloatDistribution, ParameterError> {
+        check_finite("mean", mean)?;
+        check_positive("sd", sd)?;
+        Ok(FloatDistribution::LogNormal(
+            LogNormal::new(mean, sd).unwrap(),
+        ))
+    }
+
+    pub fn exponential(rate: f64) -> Result<FloatDistribution, ParameterError> {
+        check_positive("rate", rate)?;
+        Ok(FloatDistribution::Exponential(Exp::new(rate).unwrap()))
+    }
+
+    pub fn gamma(shape: f64, scale: f64) -> Result<FloatDistribution, ParameterError> {
+        check_positive("shape", shape)?;
+        check_positive("scale", scale)?;
+        Ok(FloatDistribution::Gamma(Gamma::new(shape, scale).unwrap()))
+    }
+
+    pub fn beta(alpha: f64, beta: f64) -> Result<FloatDistribution, ParameterError> {
+        check_positive("alpha", alpha)?;
+        check_positive("beta", beta)?;
+        Ok(FloatDistribution::Beta(Beta::new(alpha, beta).unwrap()))
+    }
 
-    for (idx, line) in reader.lines().enumerate() {
-        if random_uniform() < 1.0 / ((idx + 1) as f64) {
-            selected_word.pop();
-            selected_word.push(line.unwrap())
+    pub fn cauchy(location: f64, scale: f64) -> Result<FloatDistribution, ParameterError> {
+        check_finite("location", location)?;
+        check_positive("scale", scale)?;
+        Ok(FloatDistribution::Cauchy(
+            Cauchy::new(location, scale).unwrap(),
+        ))
+    }
+
+    pub fn student_t(df: f64) -> Result<FloatDistribution, ParameterError> {
+        check_positive("df", df)?;
+        Ok(FloatDistribution::StudentT(StudentT::new(df).unwrap()))
+    }
+
+    pub fn chi_squared(df: f64) -> Result<FloatDistribution, ParameterError> {
+        check_positive("df", df)?;
+        Ok(FloatDistribution::ChiSquared(ChiSquared::new(df).unwrap()))
+    }
+
+    pub fn weibull(scale: f64, shape: f64) -> Result<FloatDistribution, ParameterError> {
+        check_positive("scale", scale)?;
+        check_positive("shape", shape)?;
+        Ok(FloatDistribution::Weibull(
+            Weibull::new(scale, shape).unwrap(),
+        ))
+    }
+
+    pub fn triangular(min: f64, max: f64, mode: f64) -> Result<FloatDistribution, ParameterError> {
+        check_finite("min", min)?;
+        check_finite("max", max)?;
+        check_finite("mode", mode)?;
+        if min >= max {
+            return Err(ParameterError {
+                parameter: "min",
+                requirement: "strictly less than max",
+            });
         }
+        if mode < min || mode > max {
+            return Err(ParameterError {
+                parameter: "mode",
+                requirement: "between min and max",
+            });
+        }
+        Ok(FloatDistribution::Triangular(
+            Triangular::new(min, max, mode).unwrap(),
+        ))
     }
 
-    Ok(selected_word.pop().unwrap())
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match self {
+            FloatDistribution::Uniform { lower, upper } => float_given_bounds(*lower, *upper, rng),
+            FloatDistribution::Normal(d) => d.sample(rng),
+            FloatDistribution::LogNormal(d) => d.sample(rng),
+            FloatDistribution::Exponential(d) => d.sample(rng),
+            FloatDistribution::Gamma(d) => d.sample(rng),
+            FloatDistribution::Beta(d) => d.sample(rng),
+            FloatDistribution::Cauchy(d) => d.sample(rng),
+            FloatDistribution::StudentT(d) => d.sample(rng),
+            FloatDistribution::ChiSquared(d) => d.sample(rng),
+            FloatDistribution::Weibull(d) => d.sample(rng),
+            FloatDistribution::Triangular(d) => d.sample(rng),
+        }
+    }
+}
+
+/// A FloatDistribution restricted to the interval `[lower, upper)` by
+/// rejecting draws that fall outside it.
+#[derive(Debug, Clone, Copy)]
+pub struct TruncatedFloat {
+    pub distribution: FloatDistribution,
+    pub lower: f64,
+    pub upper: f64,
 }
 
-fn random_uniform() -> f64 {
-    thread_rng().gen::<f64>()
+impl TruncatedFloat {
+    /// Return a value drawn from the distribution conditional on lying within
+    /// the bounds, or None if no such value turns up after
+    /// MAX_TRUNCATION_ATTEMPTS draws.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<f64> {
+        for _ in 0..MAX_TRUNCATION_ATTEMPTS {
+            let value = self.distribution.sample(rng);
+            if self.lower <= value && value < self.upper {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+/// Format a value below upper with the given number of decimal places.
+/// Rounding to the nearest such decimal can reach upper, and then the value is
+/// rounded down instead so that the output stays below upper.
+pub fn format_below(value: f64, precision: usize, upper: f64) -> String {
+    let text = format!("{:.*}", precision, value);
+    let scale = 10f64.powi(i32::try_from(precision).unwrap_or(i32::MAX));
+    match text.parse::<f64>() {
+        Ok(rounded) if rounded >= upper && scale.is_finite() => {
+            format!("{:.*}", precision, (value * scale).floor() / scale)
+        }
+        _ => text,
+    }
+}
+
+/// A discrete distribution over the non-negative integers. Each constructor
+/// checks its parameters and returns a ParameterError naming the first one
+/// that is out of range.
+#[derive(Debug, Clone, Copy)]
+pub enum IntDistribution {
+    Poisson(Poisson<f64>),
+    Binomial(Binomial),
+    /// The number of failures before `successes` successes in trials that
+    /// each succeed with probability `p`.
+    NegativeBinomial {
+        successes: u64,
+        p: f64,
+    },
+    /// The number of failures before the first success.
+    Geometric(Geometric),
+    Hypergeometric(Hypergeometric),
+    /// Ranks from 1 to the number of elements, with probability inversely
+    /// proportional to a power of the rank.
+    Zipf(Zipf<f64>),
+}
+
+impl IntDistribution {
+    /// Build the distribution with the given name, such as `poisson`, reading
+    /// counts with `integer` and other parameters with `float`, which is
+    /// given the default for a parameter that may be left out.
+    pub fn from_name<E, I, F>(
+        name: &str,
+        integer: I,
+        float: F,
+    ) -> Result<IntDistribution, DistributionError<E>>
+    where
+        I: Fn(&str) -> Result<u64, E>,
+        F: Fn(&str, Option<f64>) -> Result<f64, E>,
+    {
+        let count = |name| integer(name).map_err(DistributionError::Lookup);
+        let parameter = |name, default| float(name, default).map_err(DistributionError::Lookup);
+        match name {
+            "poisson" => IntDistribution::poisson(parameter("lambda", None)?),
+            "binomial" => IntDistribution::binomial(count("trials")?, parameter("p", None)?),
+            "negbinomial" => {
+                IntDistribution::negative_binomial(count("successes")?, parameter("p", None)?)
+            }
+            "geometric" => IntDistribution::geometric(parameter("p", None)?),
+            "hypergeometric" => IntDistribution::hypergeometric(
+                count("population")?,
+                count("successes")?,
+                count("draws")?,
+            ),
+            "zipf" => IntDistribution::zipf(count("elements")?, parameter("exponent", Some(1.0))?),
+            _ => return Err(DistributionError::Unknown),
+        }
+        .map_err(DistributionError::Invalid)
+    }
+
+    pub fn poisson(lambda: f64) -> Result<IntDistribution, ParameterError> {
+        check_positive("lambda", lambda)?;
+        Ok(IntDistribution::Poisson(Poisson::new(lambda).unwrap()))
+    }
+
+    pub fn binomial(trials: u64, p: f64) -> Result<IntDistribution, ParameterError> {
+        check_probability("p", p)?;
+        Ok(IntDistribution::Binomial(Binomial::new(trials, p).unwrap()))
+    }
+
+    pub fn negative_binomial(successes: u64, p: f64) -> Result<IntDistribution, ParameterError> {
+        if successes == 0 {
+            return Err(ParameterError {
+                parameter: "successes",
+                requirement: "at least 1",
+            });
+        }
+        check_probability("p", p)?;
+        check_positive("p", p)?;
+        Ok(IntDistribution::NegativeBinomial { successes, p })
+    }
+
+    pub fn geometric(p: f64) -> Result<IntDistribution, ParameterError> {
+        check_probability("p", p)?;
+        check_positive("p", p)?;
+        Ok(IntDistribution::Geometric(Geometric::new(p).unwrap()))
+    }
+
+    pub fn hypergeometric(
+        population: u64,
+        successes: u64,
+        draws: u64,
+    ) -> Result<IntDistribution, ParameterError> {
+        if successes > population {
+            return Err(ParameterError {
+                parameter: "successes",
+                requirement: "at most population",
+            });
+        }
+        if draws > population {
+            return Err(ParameterError {
+                parameter: "draws",
+                requirement: "at most population",
+            });
+        }
+        Ok(IntDistribution::Hypergeometric(
+            Hypergeometric::new(population, successes, draws).unwrap(),
+        ))
+    }
+
+    pub fn zipf(elements: u64, exponent: f64) -> Result<IntDistribution, ParameterError> {
+        if elements == 0 {
+            return Err(ParameterError {
+                parameter: "elements",
+                requirement: "at least 1",
+            });
+        }
+        if !(exponent.is_finite() && exponent >= 0.0) {
+            return Err(ParameterError {
+                parameter: "exponent",
+                requirement: "non-negative and finite",
+            });
+        }
+        Ok(IntDistribution::Zipf(
+            Zipf::new(elements, exponent).unwrap(),
+        ))
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        match self {
+            IntDistribution::Poisson(d) => d.sample(rng) as u64,
+            IntDistribution::Binomial(d) => d.sample(rng),
+            IntDistribution::NegativeBinomial { successes, p } => {
+                if *p == 1.0 {
+                    return 0;
+                }
+                // A Poisson draw whose rate is itself gamma distributed.
+                let rate = Gamma::new(*successes as f64, (1.0 - p) / p)
+                    .unwrap()
+                    .sample(rng);
+                match Poisson::new(rate) {
+                    Ok(d) => d.sample(rng) as u64,
+                    Err(_) => 0,
+                }
+            }
+            IntDistribution::Geometric(d) => d.sample(rng),
+            IntDistribution::Hypergeometric(d) => d.sample(rng),
+            IntDistribution::Zipf(d) => d.sample(rng) as u64,
+        }
+    }
+}
+
+fn check_probability(parameter: &'static str, value: f64) -> Result<(), ParameterError> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(ParameterError {
+            parameter,
+            requirement: "between 0 and 1",
+        });
+    }
+    Ok(())
+}
+
+fn check_finite(parameter: &'static str, value: f64) -> Result<(), ParameterError> {
+    if !value.is_finite() {
+        return Err(ParameterError {
+            parameter,
+            requirement: "finite",
+        });
+    }
+    Ok(())
+}
+
+fn check_positive(parameter: &'static str, value: f64) -> Result<(), ParameterError> {
+    if !(value.is_finite() && value > 0.0) {
+        return Err(ParameterError {
+            parameter,
+            requirement: "positive and finite",
+        });
+    }
+    Ok(())
+}
+
+pub fn integer_given_bounds<R: Rng + ?Sized>(lower: u64, upper: u64, rng: &mut R) -> u64 {
+    lower + random_below(upper - lower, rng)
+}
+
+/// Return an integer drawn uniformly from `lower`, `lower + step`, `lower +
+/// 2 * step` and so on, up to at most `upper`. The range may span the whole
+/// of `i128`.
+pub fn signed_integer_given_step<R: Rng + ?Sized>(
+    lower: i128,
+    upper: i128,
+    step: u128,
+    rng: &mut R,
+) -> i128 {
+    let steps = random_at_most(upper.wrapping_sub(lower) as u128 / step, rng);
+    lower.wrapping_add(steps.wrapping_mul(step) as i128)
+}
+
+/// Return an integer drawn uniformly from `lower`, `lower + step`, `lower +
+/// 2 * step` and so on, up to at most `upper`. The range may span the whole
+/// of `u128`.
+pub fn unsigned_integer_given_step<R: Rng + ?Sized>(
+    lower: u128,
+    upper: u128,
+    step: u128,
+    rng: &mut R,
+) -> u128 {
+    lower + random_at_most((upper - lower) / step, rng) * step
+}
+
+/// Why `k` distinct integers could not be drawn.
+#[derive(Debug, PartialEq)]
+pub enum DistinctError {
+    /// The range holds fewer than `k` values, only the number given.
+    TooFewValues(u128),
+    /// The `k` values do not fit in memory.
+    TooManyValues,
+}
+
+/// Return `k` distinct integers drawn like `signed_integer_given_step`, in
+/// random order.
+pub fn distinct_signed_integers_given_step<R: Rng + ?Sized>(
+    lower: i128,
+    upper: i128,
+    step: u128,
+    k: usize,
+    rng: &mut R,
+) -> Result<Vec<i128>, DistinctError> {
+    let max = upper.wrapping_sub(lower) as u128 / step;
+    if k > 0 && (k - 1) as u128 > max {
+        return Err(DistinctError::TooFewValues(max + 1));
+    }
+    let values = distinct_integers(max, k, rng)?
+        .into_iter()
+        .map(|steps| lower.wrapping_add(steps.wrapping_mul(step) as i128))
+        .collect();
+    Ok(values)
+}
+
+/// Return `k` distinct integers drawn like `unsigned_integer_given_step`, in
+/// random order.
+pub fn distinct_unsigned_integers_given_step<R: Rng + ?Sized>(
+    lower: u128,
+    upper: u128,
+    step: u128,
+    k: usize,
+    rng: &mut R,
+) -> Result<Vec<u128>, DistinctError> {
+    let max = (upper - lower) / step;
+    if k > 0 && (k - 1) as u128 > max {
+        return Err(DistinctError::TooFewValues(max + 1));
+    }
+    let values = distinct_integers(max, k, rng)?
+        .into_iter()
+        .map(|steps| lower + steps * step)
+        .collect();
+    Ok(values)
+}
+
+pub fn float_given_bounds<R: Rng + ?Sized>(lower: f64, upper: f64, rng: &mut R) -> f64 {
+    lower + random_uniform(rng) * (upper - lower)
+}
+
+/// Return `k` distinct integers drawn uniformly from `0..=max` in random
+/// order, which must contain at least `k` values. Floyd's algorithm makes
+/// exactly one draw per value, so no draw is ever retried. Memory for all `k`
+/// values is set aside first, so that a `k` too large to hold is an error
+/// rather than an abort partway through.
+pub fn distinct_integers<R: Rng + ?Sized>(
+    max: u128,
+    k: usize,
+    rng: &mut R,
+) -> Result<Vec<u128>, DistinctError> {
+    let mut chosen = HashSet::new();
+    let mut result = Vec::new();
+    chosen
+        .try_reserve(k)
+        .and_then(|_| result.try_reserve_exact(k))
+        .map_err(|_| DistinctError::TooManyValues)?;
+    for j in (0..k as u128).rev().map(|i| max - i) {
+        let t = random_at_most(j, rng);
+        let value = if chosen.contains(&t) { j } else { t };
+        chosen.insert(value);
+        result.push(value);
+    }
+    shuffle(&mut result, rng);
+    Ok(result)
+}
+
+/// Put the items in a uniformly random order.
+pub fn shuffle<T, R: Rng + ?Sized>(items: &mut [T], rng: &mut R) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, random_index(i + 1, rng));
+    }
+}
+
+pub fn random_index<R: Rng + ?Sized>(len: usize, rng: &mut R) -> usize {
+    random_below(len as u64, rng) as usize
+}
+
+/// Return an index into `weights` drawn with probability proportional to the
+/// weight at that index. At least one weight must be positive.
+fn weighted_index<R: Rng + ?Sized>(weights: &[u32], rng: &mut R) -> usize {
+    let total = weights.iter().map(|&w| u64::from(w)).sum();
+    let mut target = random_below(total, rng);
+    for (i, &w) in weights.iter().enumerate() {
+        if target < u64::from(w) {
+            return i;
+        }
+        target -= u64::from(w);
+    }
+    unreachable!("target is less than the total weight")
+}
+
+/// A table for drawing indexes in proportion to floating point weights in
+/// constant time, using Vose's alias method. Each index keeps itself with
+/// some probability and otherwise gives way to its alias, an index with more
+/// than its share of the weight.
+#[derive(Debug, PartialEq)]
+pub struct AliasTable {
+    probabilities: Vec<f64>,
+    aliases: Vec<usize>,
+}
+
+impl AliasTable {
+    /// Build a table from weights that are finite and not negative. Return
+    /// None if this is not the case or if the weights do not have a positive,
+    /// finite total.
+    pub fn new(weights: &[f64]) -> Option<AliasTable> {
+        let total: f64 = weights.iter().sum();
+        if weights.iter().any(|&w| !(0.0..f64::INFINITY).contains(&w))
+            || !(total > 0.0 && total.is_finite())
+        {
+            return None;
+        }
+        let len = weights.len() as f64;
+        let mut probabilities: Vec<f64> = weights.iter().map(|w| w / total * len).collect();
+        let mut aliases: Vec<usize> = (0..weights.len()).collect();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..weights.len()).partition(|&i| probabilities[i] < 1.0);
+        while let (Some(&less), Some(&more)) = (small.last(), large.last()) {
+            small.pop();
+            aliases[less] = more;
+            probabilities[more] -= 1.0 - probabilities[less];
+            if probabilities[more] < 1.0 {
+                large.pop();
+                small.push(more);
+            }
+        }
+        // Whatever is left over is only short of 1 by rounding error.
+        for i in small.into_iter().chain(large) {
+            probabilities[i] = 1.0;
+        }
+        Some(AliasTable {
+            probabilities,
+            aliases,
+        })
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let idx = random_index(self.probabilities.len(), rng);
+        if random_uniform(rng) < self.probabilities[idx] {
+            idx
+        } else {
+            self.aliases[idx]
+        }
+    }
+}
+
+/// Return an integer drawn uniformly from `0..n`. Draws of 64 random bits
+/// that fall in the incomplete final block of `n` values are rejected, so
+/// every result is exactly equally likely.
+fn random_below<R: Rng + ?Sized>(n: u64, rng: &mut R) -> u64 {
+    let rejected = (u64::MAX - n + 1) % n;
+    loop {
+        let value = rng.next_u64();
+        if value <= u64::MAX - rejected {
+            return value % n;
+        }
+    }
+}
+
+/// Return an integer drawn uniformly from `0..=max`, rejecting draws of 128
+/// random bits that would bias the result as `random_below` does.
+fn random_at_most<R: Rng + ?Sized>(max: u128, rng: &mut R) -> u128 {
+    if max < u128::from(u64::MAX) {
+        return u128::from(random_below(max as u64 + 1, rng));
+    }
+    if max == u128::MAX {
+        return rng.gen::<u128>();
+    }
+    let n = max + 1;
+    let rejected = (u128::MAX - n + 1) % n;
+    loop {
+        let value = rng.gen::<u128>();
+        if value <= u128::MAX - rejected {
+            return value % n;
+        }
+    }
+}
+
+/// Return whether a value should be left out, which happens with the given
+/// probability. No randomness is used when the rate is zero, so output with a
+/// fixed seed is the same as without a rate.
+pub fn is_missing<R: Rng + ?Sized>(rate: f64, rng: &mut R) -> bool {
+    rate > 0.0 && rng.gen_bool(rate)
+}
+
+/// Check that a rate of missing values is a probability.
+pub fn valid_null_rate(rate: f64) -> bool {
+    (0.0..=1.0).contains(&rate)
+}
+
+pub fn random_uniform<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    rng.gen::<f64>()
 }
 
 #[cfg(test)]
 mod tests {
-    use rand::{thread_rng, Rng};
+    use rand::rngs::mock::StepRng;
+    use rand::{thread_rng, Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
 
     use super::*;
 
@@ -62,7 +860,7 @@ mod tests {
     fn check_random_uniform_bounds() {
         let mut actual: f64;
         for _ in 0..100 {
-            actual = random_uniform();
+            actual = random_uniform(&mut thread_rng());
             assert!(actual < 1.0);
             assert!(actual >= 0.0);
         }
@@ -76,50 +874,594 @@ mod tests {
         for _ in 0..100 {
             lower = thread_rng().gen_range(0..100);
             upper = lower + thread_rng().gen_range(1..100);
-            result = integer_given_bounds(lower, upper);
+            result = integer_given_bounds(lower, upper, &mut thread_rng());
             assert!(result >= lower);
             assert!(result < upper);
         }
     }
 
+    #[test]
+    fn random_below_is_unbiased() {
+        let mut counts = [0u32; 3];
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..30000 {
+            counts[random_below(3, &mut rng) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&c| (9500..10500).contains(&c)));
+        assert_eq!(random_below(1, &mut rng), 0);
+        assert!(random_below(u64::MAX, &mut rng) < u64::MAX);
+        let mut rng = StepRng::new(u64::MAX, 1);
+        assert_eq!(random_below(1 << 63, &mut rng), (1 << 63) - 1);
+        let mut rng = StepRng::new(u64::MAX, 1);
+        assert_eq!(random_below(3, &mut rng), 0);
+    }
+
+    #[test]
+    fn wide_integer_sampling_respects_bounds() {
+        let mut rng = thread_rng();
+        for (lower, upper) in [
+            (-100, 100),
+            (-5, -5),
+            (i128::MIN, i128::MIN + 1),
+            (i128::MAX - 3, i128::MAX),
+            (i128::MIN, i128::MAX),
+            (-(1 << 100), 1 << 100),
+        ] {
+            for _ in 0..100 {
+                let result = signed_integer_given_step(lower, upper, 1, &mut rng);
+                assert!(lower <= result && result <= upper);
+            }
+        }
+        for (lower, upper) in [
+            (0, 1),
+            (u128::MAX - 1, u128::MAX),
+            (0, u128::MAX),
+            (1 << 64, (1 << 64) + 2),
+            (u128::from(u64::MAX), u128::from(u64::MAX) + 1),
+        ] {
+            for _ in 0..100 {
+                let result = unsigned_integer_given_step(lower, upper, 1, &mut rng);
+                assert!(lower <= result && result <= upper);
+            }
+        }
+        let mut seen = [false; 4];
+        for _ in 0..100 {
+            seen[(signed_integer_given_step(-2, 1, 1, &mut rng) + 2) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn stepped_integer_sampling_hits_every_step() {
+        let mut rng = thread_rng();
+        let mut seen = vec![];
+        for _ in 0..200 {
+            let value = signed_integer_given_step(-10, 12, 5, &mut rng);
+            assert!([-10, -5, 0, 5, 10].contains(&value));
+            seen.push(value);
+        }
+        assert!([-10, -5, 0, 5, 10].iter().all(|v| seen.contains(v)));
+        for _ in 0..100 {
+            let value = signed_integer_given_step(i128::MIN, i128::MAX, 1 << 127, &mut rng);
+            assert!(value == i128::MIN || value == 0);
+            let value = unsigned_integer_given_step(1, u128::MAX, u128::MAX / 2, &mut rng);
+            assert!([1, u128::MAX / 2 + 1, u128::MAX].contains(&value));
+        }
+    }
+
+    #[test]
+    fn weighted_index_follows_weights() {
+        let mut counts = [0u32; 4];
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..100000 {
+            counts[weighted_index(&[90, 8, 0, 2], &mut rng)] += 1;
+        }
+        assert!((89000..91000).contains(&counts[0]));
+        assert!((7500..8500).contains(&counts[1]));
+        assert_eq!(counts[2], 0);
+        assert!((1700..2300).contains(&counts[3]));
+        let mut rng = StepRng::new((1 << 33) - 2, 0);
+        assert_eq!(weighted_index(&[u32::MAX, u32::MAX, 1], &mut rng), 2);
+    }
+
+    #[test]
+    fn alias_table_follows_weights() {
+        let table = AliasTable::new(&[0.9, 0.08, 0.0, 0.02]).unwrap();
+        let mut counts = [0u32; 4];
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..100000 {
+            counts[table.sample(&mut rng)] += 1;
+        }
+        assert!((89000..91000).contains(&counts[0]));
+        assert!((7500..8500).contains(&counts[1]));
+        assert_eq!(counts[2], 0);
+        assert!((1700..2300).contains(&counts[3]));
+
+        let table = AliasTable::new(&[3.0]).unwrap();
+        assert_eq!(table.sample(&mut rng), 0);
+        assert_eq!(AliasTable::new(&[]), None);
+        assert_eq!(AliasTable::new(&[0.0, 0.0]), None);
+        assert_eq!(AliasTable::new(&[1.0, -1.0]), None);
+        assert_eq!(AliasTable::new(&[1.0, f64::NAN]), None);
+        assert_eq!(AliasTable::new(&[f64::MAX, f64::MAX]), None);
+    }
+
+    #[test]
+    fn distinct_integers_are_distinct_and_uniform() {
+        let mut rng = thread_rng();
+        let mut counts = [0u32; 10];
+        for _ in 0..2000 {
+            let values = distinct_integers(9, 5, &mut rng).unwrap();
+            assert_eq!(values.iter().collect::<HashSet<_>>().len(), 5);
+            for value in values {
+                counts[value as usize] += 1;
+            }
+        }
+        assert!(counts.iter().all(|&c| (900..1100).contains(&c)));
+
+        let mut all = distinct_integers(99, 100, &mut rng).unwrap();
+        all.sort_unstable();
+        assert_eq!(all, (0..100).collect::<Vec<_>>());
+        assert_ne!(
+            distinct_integers(99, 100, &mut rng).unwrap(),
+            distinct_integers(99, 100, &mut rng).unwrap()
+        );
+
+        let values = distinct_integers(u128::MAX, 3, &mut rng).unwrap();
+        assert_eq!(values.iter().collect::<HashSet<_>>().len(), 3);
+        assert_eq!(distinct_integers(0, 0, &mut rng), Ok(vec![]));
+        assert_eq!(distinct_integers(0, 1, &mut rng), Ok(vec![0]));
+        assert_eq!(
+            distinct_integers(u128::MAX, usize::MAX, &mut rng),
+            Err(DistinctError::TooManyValues)
+        );
+    }
+
+    #[test]
+    fn distinct_integers_given_step_cover_the_range() {
+        let mut rng = thread_rng();
+        let mut values = distinct_signed_integers_given_step(-10, 10, 5, 5, &mut rng).unwrap();
+        values.sort_unstable();
+        assert_eq!(values, vec![-10, -5, 0, 5, 10]);
+        assert_eq!(
+            distinct_signed_integers_given_step(-10, 10, 5, 6, &mut rng),
+            Err(DistinctError::TooFewValues(5))
+        );
+
+        let values =
+            distinct_signed_integers_given_step(i128::MIN, i128::MAX, 1, 3, &mut rng).unwrap();
+        assert_eq!(values.iter().collect::<HashSet<_>>().len(), 3);
+
+        let mut values = distinct_unsigned_integers_given_step(7, 9, 1, 3, &mut rng).unwrap();
+        values.sort_unstable();
+        assert_eq!(values, vec![7, 8, 9]);
+        assert_eq!(
+            distinct_unsigned_integers_given_step(7, 9, 1, 4, &mut rng),
+            Err(DistinctError::TooFewValues(3))
+        );
+        assert_eq!(
+            distinct_signed_integers_given_step(i128::MIN, i128::MAX, 1, usize::MAX, &mut rng),
+            Err(DistinctError::TooManyValues)
+        );
+        assert_eq!(
+            distinct_unsigned_integers_given_step(0, u128::MAX, 1, usize::MAX, &mut rng),
+            Err(DistinctError::TooManyValues)
+        );
+        assert_eq!(
+            distinct_unsigned_integers_given_step(0, u128::MAX, 1, 0, &mut rng),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn string_sampler_max_cardinality() {
+        let sampler = StringSampler {
+            support: vec![
+                Support::Class(CharClass::from_range('a', 'j')),
+                Support::Strings(vec![String::from("x")]),
+                Support::Alternatives {
+                    samplers: vec![
+                        StringSampler {
+                            support: vec![Support::Strings(vec![String::from("y")])],
+                            repetitions: vec![1..=1],
+                        },
+                        StringSampler {
+                            support: vec![Support::Class(CharClass::from_range('0', '9'))],
+                            repetitions: vec![2..=2],
+                        },
+                        StringSampler {
+                            support: vec![Support::Strings(vec![String::from("z")])],
+                            repetitions: vec![1..=1],
+                        },
+                    ],
+                    weights: vec![1, 1, 0],
+                },
+            ],
+            repetitions: vec![1..=2, 0..=3, 1..=1],
+        };
+        assert_eq!(sampler.max_cardinality(), 110 * 4 * 101);
+
+        let sampler = StringSampler {
+            support: vec![Support::Class(CharClass::unicode())],
+            repetitions: vec![0..=u32::MAX],
+        };
+        assert_eq!(sampler.max_cardinality(), u128::MAX);
+
+        let sampler = StringSampler {
+            support: vec![Support::Class(CharClass::new())],
+            repetitions: vec![0..=4],
+        };
+        assert_eq!(sampler.max_cardinality(), 1);
+    }
+
+    #[test]
+    fn string_sampler_sample_distinct() {
+        let mut rng = thread_rng();
+        let sampler = StringSampler {
+            support: vec![Support::Class(CharClass::from_range('0', '9'))],
+            repetitions: vec![2..=2],
+        };
+        let mut values = sampler.sample_distinct(100, &mut rng).unwrap();
+        values.sort();
+        values.dedup();
+        assert_eq!(values.len(), 100);
+
+        let sampler = StringSampler {
+            support: vec![Support::Alternatives {
+                samplers: vec![
+                    StringSampler {
+                        support: vec![Support::Strings(vec![String::from("a")])],
+                        repetitions: vec![1..=1],
+                    },
+                    StringSampler {
+                        support: vec![Support::Strings(vec![String::from("a")])],
+                        repetitions: vec![1..=1],
+                    },
+                ],
+                weights: vec![1, 1],
+            }],
+            repetitions: vec![1..=1],
+        };
+        assert_eq!(sampler.max_cardinality(), 2);
+        assert_eq!(sampler.sample_distinct(2, &mut StepRng::new(0, 1)), None);
+    }
+
+    #[test]
+    fn random_at_most_is_unbiased() {
+        let mut counts = [0u32; 3];
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..30000 {
+            counts[(random_at_most((3 << 64) - 1, &mut rng) >> 64) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&c| (9500..10500).contains(&c)));
+        let mut rng = StepRng::new(u64::MAX - 1, 1);
+        assert_eq!(random_at_most(u128::MAX / 3 * 2, &mut rng), 1 << 64);
+        let mut rng = StepRng::new(u64::MAX, 0);
+        assert_eq!(random_at_most(u128::MAX, &mut rng), u128::MAX);
+    }
+
     #[test]
     fn float_sampling_respects_bounds() {
         let mut lower: f64;
         let mut upper: f64;
         let mut result: f64;
         for _ in 0..100 {
-            lower = 100.0 * random_uniform();
-            upper = lower + 100.0 * random_uniform();
-            result = float_given_bounds(lower, upper);
+            lower = 100.0 * random_uniform(&mut thread_rng());
+            upper = lower + 100.0 * random_uniform(&mut thread_rng());
+            result = float_given_bounds(lower, upper, &mut thread_rng());
             assert!(result >= lower);
             assert!(result < upper);
         }
     }
 
+    #[test]
+    fn seeded_samples_are_stable() {
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let sampler = StringSampler {
+            support: vec![Support::Class(CharClass::from_range('a', 'z'))],
+            repetitions: vec![8..=8],
+        };
+        assert_eq!(sampler.sample(&mut rng), "xvurkxgg");
+        assert_eq!(sampler.sample(&mut rng), "tigmpqze");
+
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        assert_eq!(integer_given_bounds(0, 1000, &mut rng), 256);
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        assert_eq!(float_given_bounds(0.0, 1.0, &mut rng), 0.5140492957650241);
+    }
+
+    #[test]
+    fn float_distributions_validate_parameters() {
+        for (result, parameter) in [
+            (FloatDistribution::uniform(1.0, 1.0), "lower"),
+            (FloatDistribution::uniform(0.0, f64::INFINITY), "upper"),
+            (FloatDistribution::normal(f64::NAN, 1.0), "mean"),
+            (FloatDistribution::normal(0.0, 0.0), "sd"),
+            (FloatDistribution::log_normal(0.0, -1.0), "sd"),
+            (FloatDistribution::exponential(0.0), "rate"),
+            (FloatDistribution::gamma(-1.0, 1.0), "shape"),
+            (FloatDistribution::gamma(1.0, f64::INFINITY), "scale"),
+            (FloatDistribution::beta(1.0, 0.0), "beta"),
+            (FloatDistribution::cauchy(0.0, -2.0), "scale"),
+            (FloatDistribution::student_t(0.0), "df"),
+            (FloatDistribution::chi_squared(-3.0), "df"),
+            (FloatDistribution::weibull(0.0, 1.0), "scale"),
+            (FloatDistribution::triangular(1.0, 0.0, 0.5), "min"),
+            (FloatDistribution::triangular(0.0, 1.0, 2.0), "mode"),
+        ] {
+            assert_eq!(result.unwrap_err().parameter, parameter);
+        }
+        assert_eq!(
+            FloatDistribution::normal(0.0, 0.0).unwrap_err().to_string(),
+            "sd must be positive and finite"
+        );
+    }
+
+    #[test]
+    fn float_distributions_sample_within_support() {
+        let mut rng = thread_rng();
+        for (distribution, lower, upper) in [
+            (FloatDistribution::uniform(-2.0, 3.0), -2.0, 3.0),
+            (FloatDistribution::log_normal(0.0, 1.0), 0.0, f64::INFINITY),
+            (FloatDistribution::exponential(2.0), 0.0, f64::INFINITY),
+            (FloatDistribution::gamma(2.0, 3.0), 0.0, f64::INFINITY),
+            (FloatDistribution::beta(0.5, 2.0), 0.0, 1.0),
+            (FloatDistribution::chi_squared(4.0), 0.0, f64::INFINITY),
+            (FloatDistribution::weibull(1.0, 1.5), 0.0, f64::INFINITY),
+            (FloatDistribution::triangular(1.0, 4.0, 2.0), 1.0, 4.0),
+        ] {
+            let distribution = distribution.unwrap();
+            for _ in 0..100 {
+                let value = distribution.sample(&mut rng);
+                assert!(lower <= value && value <= upper, "{:?}", distribution);
+            }
+        }
+        let normal = FloatDistribution::normal(10.0, 2.0).unwrap();
+        let mean = (0..10000).map(|_| normal.sample(&mut rng)).sum::<f64>() / 10000.0;
+        assert!((mean - 10.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn int_distributions_validate_parameters() {
+        for (result, parameter) in [
+            (IntDistribution::poisson(0.0), "lambda"),
+            (IntDistribution::poisson(f64::NAN), "lambda"),
+            (IntDistribution::binomial(10, 1.5), "p"),
+            (IntDistribution::negative_binomial(0, 0.5), "successes"),
+            (IntDistribution::negative_binomial(3, 0.0), "p"),
+            (IntDistribution::geometric(-0.1), "p"),
+            (IntDistribution::hypergeometric(10, 11, 2), "successes"),
+            (IntDistribution::hypergeometric(10, 5, 11), "draws"),
+            (IntDistribution::zipf(0, 1.0), "elements"),
+            (IntDistribution::zipf(10, -1.0), "exponent"),
+        ] {
+            assert_eq!(result.unwrap_err().parameter, parameter);
+        }
+    }
+
+    #[test]
+    fn distributions_are_built_from_names() {
+        let float = |name: &str, default: Option<f64>| match name {
+            "p" => Ok(0.5),
+            "df" => Ok(-1.0),
+            _ => default.ok_or(name.to_string()),
+        };
+        let integer = |name: &str| match name {
+            "trials" => Ok(4),
+            _ => Err(name.to_string()),
+        };
+        assert!(IntDistribution::from_name("binomial", integer, float).is_ok());
+        assert!(FloatDistribution::from_name("normal", float).is_ok());
+        assert_eq!(
+            IntDistribution::from_name("zipf", integer, float).unwrap_err(),
+            DistributionError::Lookup(String::from("elements"))
+        );
+        assert_eq!(
+            FloatDistribution::from_name("t", float).unwrap_err(),
+            DistributionError::Invalid(ParameterError {
+                parameter: "df",
+                requirement: "positive and finite",
+            })
+        );
+        assert_eq!(
+            FloatDistribution::from_name("pareto", float).unwrap_err(),
+            DistributionError::Unknown
+        );
+    }
+
+    #[test]
+    fn int_distributions_sample_within_support() {
+        let mut rng = thread_rng();
+        for (distribution, lower, upper) in [
+            (IntDistribution::binomial(10, 0.3), 0, 10),
+            (IntDistribution::binomial(5, 1.0), 5, 5),
+            (IntDistribution::hypergeometric(20, 5, 8), 0, 5),
+            (IntDistribution::hypergeometric(20, 15, 8), 3, 8),
+            (IntDistribution::zipf(100, 1.2), 1, 100),
+            (IntDistribution::negative_binomial(4, 1.0), 0, 0),
+            (IntDistribution::geometric(1.0), 0, 0),
+        ] {
+            let distribution = distribution.unwrap();
+            for _ in 0..100 {
+                let value = distribution.sample(&mut rng);
+                assert!(lower <= value && value <= upper, "{:?}", distribution);
+            }
+        }
+        for (distribution, expected_mean) in [
+            (IntDistribution::poisson(4.0), 4.0),
+            (IntDistribution::negative_binomial(3, 0.25), 9.0),
+            (IntDistribution::geometric(0.2), 4.0),
+        ] {
+            let distribution = distribution.unwrap();
+            let mean = (0..20000)
+                .map(|_| distribution.sample(&mut rng) as f64)
+                .sum::<f64>()
+                / 20000.0;
+            assert!((mean - expected_mean).abs() < 0.3, "{:?}", distribution);
+        }
+    }
+
+    #[test]
+    fn truncated_float_respects_bounds() {
+        let mut rng = thread_rng();
+        let truncated = TruncatedFloat {
+            distribution: FloatDistribution::normal(0.0, 1.0).unwrap(),
+            lower: -0.5,
+            upper: 2.0,
+        };
+        for _ in 0..100 {
+            let value = truncated.sample(&mut rng).unwrap();
+            assert!((-0.5..2.0).contains(&value));
+        }
+        let truncated = TruncatedFloat {
+            distribution: FloatDistribution::uniform(0.0, 1.0).unwrap(),
+            lower: 5.0,
+            upper: 6.0,
+        };
+        assert_eq!(truncated.sample(&mut rng), None);
+    }
+
+    #[test]
+    fn format_below_stays_below_upper() {
+        assert_eq!(format_below(0.994, 2, 1.0), "0.99");
+        assert_eq!(format_below(0.996, 2, 1.0), "0.99");
+        assert_eq!(format_below(0.996, 2, f64::INFINITY), "1.00");
+        assert_eq!(format_below(-1.25, 1, -1.2), "-1.3");
+        assert_eq!(format_below(7.0, 0, 8.0), "7");
+        assert_eq!(format_below(7.6, 0, 8.0), "7");
+    }
+
+    #[test]
+    fn string_sampler_max_length() {
+        let sampler = StringSampler {
+            support: vec![
+                Support::Strings(vec![String::from("ab"), String::from("c")]),
+                Support::Strings(vec![String::from("xyz")]),
+            ],
+            repetitions: vec![1..=4, 0..=2],
+        };
+        assert_eq!(sampler.max_length(), 14);
+
+        let sampler = StringSampler {
+            support: vec![
+                Support::Strings(vec![String::from("ab")]),
+                Support::Strings(vec![String::from("c")]),
+            ],
+            repetitions: vec![0..=u32::MAX, 0..=u32::MAX],
+        };
+        assert_eq!(sampler.max_length(), 3 * u64::from(u32::MAX));
+    }
+
+    #[test]
+    fn string_sampler_empty_support() {
+        let sampler = StringSampler {
+            support: vec![
+                Support::Strings(vec![String::from("ab")]),
+                Support::Alternatives {
+                    samplers: vec![StringSampler {
+                        support: vec![Support::Class(CharClass::new())],
+                        repetitions: vec![1..=1],
+                    }],
+                    weights: vec![1],
+                },
+            ],
+            repetitions: vec![1..=1, 1..=1],
+        };
+        assert!(sampler.has_empty_support());
+
+        let sampler = StringSampler {
+            support: vec![Support::Alternatives {
+                samplers: vec![
+                    StringSampler {
+                        support: vec![Support::Class(CharClass::new())],
+                        repetitions: vec![1..=1],
+                    },
+                    StringSampler {
+                        support: vec![Support::Strings(vec![String::from("ab")])],
+                        repetitions: vec![1..=1],
+                    },
+                ],
+                weights: vec![0, 1],
+            }],
+            repetitions: vec![1..=1],
+        };
+        assert!(!sampler.has_empty_support());
+
+        let sampler = StringSampler {
+            support: vec![Support::Class(CharClass::from_range('a', 'b'))],
+            repetitions: vec![1..=1],
+        };
+        assert!(!sampler.has_empty_support());
+    }
+
     #[test]
     fn string_sample() {
         let mut sampler: StringSampler;
         let mut result: String;
 
         sampler = StringSampler {
-            support: vec![vec![String::from("abc")]],
-            repetitions: vec![1],
+            support: vec![Support::Strings(vec![String::from("abc")])],
+            repetitions: vec![1..=1],
         };
-        result = sampler.sample();
+        result = sampler.sample(&mut thread_rng());
         assert_eq!(result, String::from("abc"));
 
         sampler = StringSampler {
-            support: vec![vec![String::from("abc")]],
-            repetitions: vec![3],
+            support: vec![Support::Strings(vec![String::from("abc")])],
+            repetitions: vec![3..=3],
         };
-        result = sampler.sample();
+        result = sampler.sample(&mut thread_rng());
         assert_eq!(result, String::from("abcabcabc"));
 
         sampler = StringSampler {
-            support: vec![vec![String::from("a"), String::from("z")]],
-            repetitions: vec![2],
+            support: vec![Support::Strings(vec![String::from("a"), String::from("z")])],
+            repetitions: vec![2..=2],
         };
-        result = sampler.sample();
+        result = sampler.sample(&mut thread_rng());
         assert!(result == *"aa" || result == *"zz" || result == *"az" || result == *"za");
+
+        sampler = StringSampler {
+            support: vec![Support::Strings(vec![String::from("ab")])],
+            repetitions: vec![0..=3],
+        };
+        for _ in 0..100 {
+            result = sampler.sample(&mut thread_rng());
+            assert!(["", "ab", "abab", "ababab"].contains(&result.as_str()));
+        }
+
+        sampler = StringSampler {
+            support: vec![
+                Support::Alternatives {
+                    samplers: vec![
+                        StringSampler {
+                            support: vec![Support::Strings(vec![String::from("x")])],
+                            repetitions: vec![2..=2],
+                        },
+                        StringSampler {
+                            support: vec![Support::Strings(vec![String::from("yz")])],
+                            repetitions: vec![1..=1],
+                        },
+                    ],
+                    weights: vec![1, 1],
+                },
+                Support::Strings(vec![String::from("!")]),
+            ],
+            repetitions: vec![2..=2, 1..=1],
+        };
+        for _ in 0..100 {
+            result = sampler.sample(&mut thread_rng());
+            assert!(["xxxx!", "xxyz!", "yzxx!", "yzyz!"].contains(&result.as_str()));
+        }
+    }
+
+    #[test]
+    fn missing_values_follow_rate() {
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        assert!(!(0..1000).any(|_| is_missing(0.0, &mut rng)));
+        assert!((0..1000).all(|_| is_missing(1.0, &mut rng)));
+        let missing = (0..10_000).filter(|_| is_missing(0.2, &mut rng)).count();
+        assert!((1800..2200).contains(&missing), "{}", missing);
+        assert!(valid_null_rate(0.0) && valid_null_rate(1.0));
+        assert!(!valid_null_rate(-0.1) && !valid_null_rate(1.5) && !valid_null_rate(f64::NAN));
     }
 }
@@ -0,0 +1,720 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use rand::Rng;
+use toml::value::Table;
+use toml::Value as TomlValue;
+
+use crate::class::CharClass;
+use crate::pattern::{Pattern, SamplerError};
+use crate::sample::{
+    self, AliasTable, DistributionError, FloatDistribution, IntDistribution, StringSampler,
+    TruncatedFloat,
+};
+use crate::wordlist::{Column as WordColumn, Wordlist};
+
+/// The columns of a record, read from a TOML file with one `[[column]]`
+/// table per column, in order:
+///
+/// ```toml
+/// [[column]]
+/// name = "id"
+/// type = "int"
+/// lower = 1
+/// upper = 1000
+/// ```
+///
+/// Each column names a generator with `type`, one of `int`, `float`,
+/// `string` or `word`, and takes the same parameters as the subcommand of
+/// that name.
+#[derive(Debug)]
+pub struct Schema {
+    pub columns: Vec<Column>,
+}
+
+#[derive(Debug)]
+pub struct Column {
+    pub name: String,
+    null_rate: Option<f64>,
+    generator: Generator,
+}
+
+#[derive(Debug)]
+enum Generator {
+    Int {
+        lower: i128,
+        upper: i128,
+        step: u128,
+    },
+    IntDistribution(IntDistribution),
+    Float {
+        sampler: TruncatedFloat,
+        precision: Option<usize>,
+    },
+    String(StringSampler),
+    Word(Wordlist),
+    WeightedWord {
+        words: Wordlist,
+        table: AliasTable,
+    },
+}
+
+/// A sampled field, kept as text but marked as a number when it should be
+/// written without quotes, or a missing value.
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    Number(String),
+    Text(String),
+    Null,
+}
+
+impl Value {
+    pub fn text(&self) -> &str {
+        match self {
+            Value::Number(text) | Value::Text(text) => text,
+            Value::Null => "",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SchemaError {
+    pub column: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.column {
+            Some(column) => write!(f, "column {}: {}", column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+const INT_KEYS: &[&str] = &[
+    "lower",
+    "upper",
+    "inclusive",
+    "step",
+    "dist",
+    "lambda",
+    "p",
+    "trials",
+    "successes",
+    "population",
+    "draws",
+    "elements",
+    "exponent",
+];
+const FLOAT_KEYS: &[&str] = &[
+    "lower",
+    "upper",
+    "precision",
+    "dist",
+    "mean",
+    "sd",
+    "rate",
+    "shape",
+    "scale",
+    "alpha",
+    "beta",
+    "location",
+    "df",
+    "min",
+    "max",
+    "mode",
+];
+const STRING_KEYS: &[&str] = &["pattern", "unicode"];
+const WORD_KEYS: &[&str] = &["file", "list", "column", "delimiter", "weighted"];
+
+impl Schema {
+    /// Read a schema from a file. Wordlists named by relative paths are
+    /// found relative to the directory of the schema.
+    pub fn from_file(path: &str) -> Result<Schema, SchemaError> {
+        let text = fs::read_to_string(path).map_err(|err| SchemaError {
+            column: None,
+            message: format!("unable to read schema: {}", err),
+        })?;
+        let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+        Schema::parse(&text, directory)
+    }
+
+    pub fn parse(text: &str, directory: &Path) -> Result<Schema, SchemaError> {
+        let error = |message: String| SchemaError {
+            column: None,
+            message,
+        };
+        let document = text
+            .parse::<TomlValue>()
+            .map_err(|err| error(format!("invalid TOML: {}", err)))?;
+        let tables = match document.get("column") {
+            Some(TomlValue::Array(tables)) if !tables.is_empty() => tables,
+            _ => {
+                return Err(error(String::from(
+                    "expected one or more [[column]] tables",
+                )))
+            }
+        };
+        let mut columns: Vec<Column> = vec![];
+        for (idx, table) in tables.iter().enumerate() {
+            let table = table
+                .as_table()
+                .ok_or_else(|| error(format!("column {} is not a table", idx + 1)))?;
+            let name = match table.get("name") {
+                Some(TomlValue::String(name)) => name.clone(),
+                _ => return Err(error(format!("column {} has no name", idx + 1))),
+            };
+            if columns.iter().any(|column| column.name == name) {
+                return Err(error(format!("column {} is defined twice", name)));
+            }
+            let parameters = Parameters {
+                column: &name,
+                table,
+            };
+            let null_rate = parameters.null_rate()?;
+            let generator = parameters.generator(directory)?;
+            columns.push(Column {
+                name,
+                null_rate,
+                generator,
+            });
+        }
+        Ok(Schema { columns })
+    }
+
+    pub fn header(&self) -> Vec<&str> {
+        self.columns
+            .iter()
+            .map(|column| column.name.as_str())
+            .collect()
+    }
+
+    /// Return one value for each column, each missing at the null_rate of its
+    /// column or else at the given rate. A float column truncated to bounds
+    /// that its distribution never reaches is an error.
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        null_rate: f64,
+        rng: &mut R,
+    ) -> Result<Vec<Value>, SchemaError> {
+        self.columns
+            .iter()
+            .map(|column| {
+                if sample::is_missing(column.null_rate.unwrap_or(null_rate), rng) {
+                    return Ok(Value::Null);
+                }
+                column.generator.sample(rng).ok_or_else(|| SchemaError {
+                    column: Some(column.name.clone()),
+                    message: format!(
+                        "no value between lower and upper after {} draws",
+                        sample::MAX_TRUNCATION_ATTEMPTS
+                    ),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Generator {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Value> {
+        let value = match self {
+            Generator::Int { lower, upper, step } => Value::Number(
+                sample::signed_integer_given_step(*lower, *upper, *step, rng).to_string(),
+            ),
+            Generator::IntDistribution(distribution) => {
+                Value::Number(distribution.sample(rng).to_string())
+            }
+            Generator::Float { sampler, precision } => {
+                let value = sampler.sample(rng)?;
+                let text = match precision {
+                    Some(precision) => sample::format_below(value, *precision, sampler.upper),
+                    None => value.to_string(),
+                };
+                if value.is_finite() {
+                    Value::Number(text)
+                } else {
+                    Value::Text(text)
+                }
+            }
+            Generator::String(sampler) => Value::Text(sampler.sample(rng)),
+            Generator::Word(wordlist) => Value::Text(String::from(wordlist.sample(rng))),
+            Generator::WeightedWord { words, table } => {
+                Value::Text(String::from(words.get(table.sample(rng))))
+            }
+        };
+        Some(value)
+    }
+}
+
+/// The table of a single column, read with errors that name the column.
+struct Parameters<'a> {
+    column: &'a str,
+    table: &'a Table,
+}
+
+impl<'a> Parameters<'a> {
+    fn error(&self, message: String) -> SchemaError {
+        SchemaError {
+            column: Some(String::from(self.column)),
+            message,
+        }
+    }
+
+    fn generator(&self, directory: &Path) -> Result<Generator, SchemaError> {
+        let kind = self
+            .string("type")?
+            .ok_or_else(|| self.error(String::from("type is required")))?;
+        let keys = match kind {
+            "int" => INT_KEYS,
+            "float" => FLOAT_KEYS,
+            "string" => STRING_KEYS,
+            "word" => WORD_KEYS,
+            _ => {
+                return Err(self.error(format!(
+                    "unknown type {}, expected int, float, string or word",
+                    kind
+                )))
+            }
+        };
+        for key in self.table.keys() {
+            if !["name", "type", "null_rate"].contains(&key.as_str())
+                && !keys.contains(&key.as_str())
+            {
+                return Err(self.error(format!("unknown parameter {} for type {}", key, kind)));
+            }
+        }
+        match kind {
+            "int" => self.int_generator(),
+            "float" => self.float_generator(),
+            "string" => self.string_generator(),
+            _ => self.word_generator(directory),
+        }
+    }
+
+    fn null_rate(&self) -> Result<Option<f64>, SchemaError> {
+        match self.table.get("null_rate") {
+            Some(_) => {
+                let rate = self.float("", "null_rate", None)?;
+                if !sample::valid_null_rate(rate) {
+                    return Err(self.error(String::from("null_rate must be between 0 and 1")));
+                }
+                Ok(Some(rate))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn distribution_error(&self, dist: &str, err: DistributionError<SchemaError>) -> SchemaError {
+        match err {
+            DistributionError::Unknown => self.error(format!("unknown distribution {}", dist)),
+            DistributionError::Lookup(err) => err,
+            DistributionError::Invalid(err) => self.error(err.to_string()),
+        }
+    }
+
+    fn int_generator(&self) -> Result<Generator, SchemaError> {
+        let dist = self.string("dist")?.unwrap_or("uniform");
+        if dist == "uniform" {
+            let inclusive = self.boolean("inclusive")?;
+            let lower: i128 = self.integer("lower", Some(0))?;
+            let upper: i128 = self.integer("upper", Some(if inclusive { 1 } else { 2 }))?;
+            let step: u128 = self.integer("step", Some(1))?;
+            if step == 0 {
+                return Err(self.error(String::from("step must be at least 1")));
+            }
+            if inclusive && lower > upper {
+                return Err(self.error(String::from("lower must not be greater than upper")));
+            }
+            if !inclusive && lower >= upper {
+                return Err(self.error(String::from("lower must be strictly less than upper")));
+            }
+            let upper = if inclusive { upper } else { upper - 1 };
+            return Ok(Generator::Int { lower, upper, step });
+        }
+        if ["lower", "upper", "inclusive", "step"]
+            .iter()
+            .any(|key| self.table.contains_key(*key))
+        {
+            return Err(self.error(String::from(
+                "lower, upper, inclusive and step only apply to the uniform distribution",
+            )));
+        }
+        let distribution = IntDistribution::from_name(
+            dist,
+            |name| self.required_integer(dist, name),
+            |name, default| self.float(dist, name, default),
+        )
+        .map_err(|err| self.distribution_error(dist, err))?;
+        Ok(Generator::IntDistribution(distribution))
+    }
+
+    fn float_generator(&self) -> Result<Generator, SchemaError> {
+        let dist = self.string("dist")?.unwrap_or("uniform");
+        let uniform = dist == "uniform";
+        let lower = self.float(
+            dist,
+            "lower",
+            Some(if uniform { 0.0 } else { f64::NEG_INFINITY }),
+        )?;
+        let upper = self.float(
+            dist,
+            "upper",
+            Some(if uniform { 1.0 } else { f64::INFINITY }),
+        )?;
+        if lower >= upper {
+            return Err(self.error(String::from("lower must be strictly less than upper")));
+        }
+        let precision = match self.table.get("precision") {
+            Some(_) => Some(self.integer::<usize>("precision", None)?),
+            None => None,
+        };
+        let distribution =
+            FloatDistribution::from_name(dist, |name, default| self.float(dist, name, default))
+                .map_err(|err| self.distribution_error(dist, err))?;
+        Ok(Generator::Float {
+            sampler: TruncatedFloat {
+                distribution,
+                lower,
+                upper,
+            },
+            precision,
+        })
+    }
+
+    fn string_generator(&self) -> Result<Generator, SchemaError> {
+        let pattern = self.string("pattern")?.unwrap_or("[A-Za-z0-9]{10}");
+        let universe = if self.boolean("unicode")? {
+            CharClass::unicode()
+        } else {
+            CharClass::printable_ascii()
+        };
+        let sampler = Pattern::parse_sampler(pattern, &universe).map_err(|err| match err {
+            SamplerError::Parse(err) => self.error(format!(
+                "unable to parse pattern:\n{}",
+                err.annotate(pattern)
+            )),
+            err => self.error(err.to_string()),
+        })?;
+        Ok(Generator::String(sampler))
+    }
+
+    fn word_generator(&self, directory: &Path) -> Result<Generator, SchemaError> {
+        let mut wordlist = match (self.table.get("file"), self.string("list")?) {
+            (Some(_), Some(_)) => {
+                return Err(self.error(String::from("give either file or list, not both")))
+            }
+            (None, None) => return Err(self.error(String::from("file or list is required"))),
+            (None, Some(list)) => Wordlist::builtin(list)
+                .ok_or_else(|| self.error(format!("no built-in wordlist named {}", list)))?,
+            (Some(files), None) => {
+                let files = match files {
+                    TomlValue::String(file) => vec![file.as_str()],
+                    TomlValue::Array(files) => files
+                        .iter()
+                        .map(|file| file.as_str())
+                        .collect::<Option<Vec<&str>>>()
+                        .ok_or_else(|| self.error(String::from("file must be a list of paths")))?,
+                    _ => {
+                        return Err(self.error(String::from("file must be a path or list of paths")))
+                    }
+                };
+                let mut wordlist = Wordlist::new();
+                for file in files {
+                    let path = directory.join(file);
+                    let words = path
+                        .to_str()
+                        .map_or_else(
+                            || Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+                            Wordlist::from_file,
+                        )
+                        .map_err(|err| self.error(format!("unable to read {}: {}", file, err)))?;
+                    wordlist.append(words);
+                }
+                wordlist
+            }
+        };
+        let delimiter = match self.string("delimiter")? {
+            None => '\t',
+            Some(delimiter) => {
+                let mut chars = delimiter.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(delimiter), None) => delimiter,
+                    _ => {
+                        return Err(self.error(String::from("delimiter must be a single character")))
+                    }
+                }
+            }
+        };
+        let column = match self.table.get("column") {
+            Some(_) => match self.integer::<usize>("column", None)? {
+                0 => return Err(self.error(String::from("columns are counted from 1"))),
+                index => Some(WordColumn {
+                    index: index - 1,
+                    delimiter,
+                }),
+            },
+            None => None,
+        };
+        if self.boolean("weighted")? {
+            let (words, weights) = wordlist
+                .split_weights(delimiter, column.as_ref())
+                .map_err(|err| self.error(err.to_string()))?;
+            let table = AliasTable::new(&weights).ok_or_else(|| {
+                self.error(String::from(
+                    "wordlist contains no words with a positive weight",
+                ))
+            })?;
+            return Ok(Generator::WeightedWord { words, table });
+        }
+        if let Some(column) = column {
+            wordlist = wordlist.select(&column);
+        }
+        if wordlist.is_empty() {
+            return Err(self.error(String::from("wordlist contains no words")));
+        }
+        Ok(Generator::Word(wordlist))
+    }
+
+    fn string(&self, name: &str) -> Result<Option<&'a str>, SchemaError> {
+        match self.table.get(name) {
+            None => Ok(None),
+            Some(TomlValue::String(value)) => Ok(Some(value)),
+            Some(_) => Err(self.error(format!("{} must be a string", name))),
+        }
+    }
+
+    fn boolean(&self, name: &str) -> Result<bool, SchemaError> {
+        match self.table.get(name) {
+            None => Ok(false),
+            Some(TomlValue::Boolean(value)) => Ok(*value),
+            Some(_) => Err(self.error(format!("{} must be true or false", name))),
+        }
+    }
+
+    /// Return a parameter that must be an integer which fits in `T`, or
+    /// `default` if it is missing.
+    fn integer<T: TryFrom<i64>>(&self, name: &str, default: Option<T>) -> Result<T, SchemaError> {
+        match self.table.get(name) {
+            None => default.ok_or_else(|| self.error(format!("{} is required", name))),
+            Some(TomlValue::Integer(value)) => {
+                T::try_from(*value).map_err(|_| self.error(format!("{} is out of range", name)))
+            }
+            Some(_) => Err(self.error(format!("{} must be an integer", name))),
+        }
+    }
+
+    fn required_integer<T: TryFrom<i64>>(&self, dist: &str, name: &str) -> Result<T, SchemaError> {
+        if !self.table.contains_key(name) {
+            return Err(self.error(format!("the {} distribution requires {}", dist, name)));
+        }
+        self.integer(name, None)
+    }
+
+    /// Return a parameter that may be written as an integer or a float, or
+    /// `default` if it is missing.
+    fn float(&self, dist: &str, name: &str, default: Option<f64>) -> Result<f64, SchemaError> {
+        match self.table.get(name) {
+            None => default
+                .ok_or_else(|| self.error(format!("the {} distribution requires {}", dist, name))),
+            Some(TomlValue::Integer(value)) => Ok(*value as f64),
+            Some(TomlValue::Float(value)) => Ok(*value),
+            Some(_) => Err(self.error(format!("{} must be a number", name))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    fn parse(text: &str) -> Result<Schema, SchemaError> {
+        Schema::parse(text, Path::new(""))
+    }
+
+    fn error(column: &str, message: &str) -> SchemaError {
+        SchemaError {
+            column: Some(String::from(column)),
+            message: String::from(message),
+        }
+    }
+
+    #[test]
+    fn columns_sample_their_generators() {
+        let schema = parse(
+            r#"
+            [[column]]
+            name = "id"
+            type = "int"
+            lower = -5
+            upper = 5
+            step = 5
+            inclusive = true
+
+            [[column]]
+            name = "count"
+            type = "int"
+            dist = "binomial"
+            trials = 3
+            p = 0.5
+
+            [[column]]
+            name = "score"
+            type = "float"
+            dist = "normal"
+            lower = 0
+            precision = 2
+
+            [[column]]
+            name = "code"
+            type = "string"
+            pattern = "[A-Z]{3}"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(schema.header(), vec!["id", "count", "score", "code"]);
+        for _ in 0..100 {
+            let values = schema.sample(0.0, &mut thread_rng()).unwrap();
+            match &values[..] {
+                [Value::Number(id), Value::Number(count), Value::Number(score), Value::Text(code)] =>
+                {
+                    assert!(["-5", "0", "5"].contains(&id.as_str()));
+                    assert!(count.parse::<u64>().unwrap() <= 3);
+                    assert!(score.parse::<f64>().unwrap() >= 0.0);
+                    assert_eq!(score.split('.').nth(1).unwrap().len(), 2);
+                    assert!(code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase()));
+                }
+                _ => panic!("unexpected values {:?}", values),
+            }
+        }
+    }
+
+    #[test]
+    fn rounded_floats_stay_below_upper() {
+        let schema =
+            parse("[[column]]\nname = \"x\"\ntype = \"float\"\nlower = 0.995\nprecision = 2")
+                .unwrap();
+        for _ in 0..100 {
+            assert_eq!(
+                schema.sample(0.0, &mut thread_rng()).unwrap(),
+                vec![Value::Number(String::from("0.99"))]
+            );
+        }
+    }
+
+    #[test]
+    fn null_rates_apply_per_column() {
+        let schema = parse(
+            r#"
+            [[column]]
+            name = "id"
+            type = "int"
+            lower = 3
+            upper = 4
+            null_rate = 0
+
+            [[column]]
+            name = "code"
+            type = "string"
+            pattern = "x"
+            "#,
+        )
+        .unwrap();
+        for _ in 0..20 {
+            let values = schema.sample(1.0, &mut thread_rng()).unwrap();
+            assert_eq!(values[0], Value::Number(String::from("3")));
+            assert_eq!(values[1], Value::Null);
+        }
+        let schema = parse("[[column]]\nname = \"x\"\ntype = \"int\"\nnull_rate = 1").unwrap();
+        assert_eq!(
+            schema.sample(0.0, &mut thread_rng()).unwrap(),
+            vec![Value::Null]
+        );
+    }
+
+    #[test]
+    fn word_columns_read_files_relative_to_the_schema() {
+        let directory = env::temp_dir().join(format!("rdg-schema-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("names.tsv"), "1\tAnn\t3\n2\tBob\t0\n").unwrap();
+        fs::write(
+            directory.join("schema.toml"),
+            "[[column]]\nname = \"name\"\ntype = \"word\"\nfile = \"names.tsv\"\ncolumn = 2\nweighted = true\n",
+        )
+        .unwrap();
+        let schema = Schema::from_file(directory.join("schema.toml").to_str().unwrap()).unwrap();
+        for _ in 0..20 {
+            assert_eq!(
+                schema.sample(0.0, &mut thread_rng()).unwrap(),
+                vec![Value::Text(String::from("Ann"))]
+            );
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn invalid_schemas_are_reported() {
+        assert!(parse("column = 1").is_err());
+        assert!(parse("[[column]]\ntype = \"int\"").is_err());
+        let cases = vec![
+            (
+                "type = \"uuid\"",
+                "unknown type uuid, expected int, float, string or word",
+            ),
+            (
+                "type = \"int\"\nmean = 1",
+                "unknown parameter mean for type int",
+            ),
+            (
+                "type = \"int\"\nlower = 2\nupper = 2",
+                "lower must be strictly less than upper",
+            ),
+            ("type = \"int\"\nlower = \"1\"", "lower must be an integer"),
+            (
+                "type = \"int\"\nnull_rate = 1.5",
+                "null_rate must be between 0 and 1",
+            ),
+            (
+                "type = \"int\"\ndist = \"zipf\"",
+                "the zipf distribution requires elements",
+            ),
+            (
+                "type = \"int\"\ndist = \"zipf\"\nelements = -1",
+                "elements is out of range",
+            ),
+            (
+                "type = \"int\"\ndist = \"poisson\"\nlambda = 1\nlower = 0",
+                "lower, upper, inclusive and step only apply to the uniform distribution",
+            ),
+            (
+                "type = \"float\"\ndist = \"beta\"\nalpha = 1\nbeta = 0",
+                "beta must be positive and finite",
+            ),
+            (
+                "type = \"string\"\npattern = \"[^\\\\x00-\\\\u{10FFFF}]\"",
+                "pattern contains a character class that matches no characters",
+            ),
+            ("type = \"word\"", "file or list is required"),
+            (
+                "type = \"word\"\nfile = \"/nonexistent/rdg\"\nlist = \"short\"",
+                "give either file or list, not both",
+            ),
+        ];
+        for (table, message) in cases {
+            let text = format!("[[column]]\nname = \"x\"\n{}", table);
+            assert_eq!(parse(&text).unwrap_err(), error("x", message), "{}", table);
+        }
+        let duplicate =
+            "[[column]]\nname = \"x\"\ntype = \"int\"\n[[column]]\nname = \"x\"\ntype = \"int\"";
+        assert_eq!(
+            parse(duplicate).unwrap_err().message,
+            "column x is defined twice"
+        );
+    }
+}
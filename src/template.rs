@@ -0,0 +1,404 @@
+use std::fmt;
+
+use rand::Rng;
+
+use crate::class::CharClass;
+use crate::pattern::{self, ParseErrorKind, Pattern, SamplerError};
+use crate::sample::{self, StringSampler};
+use crate::wordlist::Wordlist;
+
+/// A line of literal text with placeholders such as `{int:1:100}` that are
+/// replaced by a freshly sampled value each time the template is expanded.
+#[derive(Debug)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    Int {
+        lower: i128,
+        upper: i128,
+    },
+    Float {
+        lower: f64,
+        upper: f64,
+        precision: Option<usize>,
+    },
+    Word(Wordlist),
+    String(StringSampler),
+}
+
+/// The reason a template could not be parsed together with the position of
+/// the offending character, counted as for `pattern::ParseError`.
+#[derive(Debug, PartialEq)]
+pub struct TemplateError {
+    pub kind: TemplateErrorKind,
+    pub offset: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TemplateErrorKind {
+    UnbalancedBrace,
+    UnknownGenerator,
+    InvalidArguments(&'static str),
+    Pattern(ParseErrorKind),
+    EmptyClass,
+    PatternTooLong,
+    UnreadableWordlist(String),
+    EmptyWordlist,
+}
+
+impl TemplateError {
+    fn new(kind: TemplateErrorKind, offset: usize) -> TemplateError {
+        TemplateError { kind, offset }
+    }
+
+    /// Return the template with a caret beneath the character at fault
+    /// followed by a description of the error.
+    pub fn annotate(&self, template: &str) -> String {
+        pattern::annotate(template, self.offset, &self.kind)
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at character {}", self.kind, self.offset)
+    }
+}
+
+impl fmt::Display for TemplateErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateErrorKind::UnbalancedBrace => {
+                write!(
+                    f,
+                    "unbalanced brace, write {{{{ or }}}} for a literal brace"
+                )
+            }
+            TemplateErrorKind::UnknownGenerator => {
+                write!(f, "unknown generator, expected int, float, word or string")
+            }
+            TemplateErrorKind::InvalidArguments(usage) => {
+                write!(f, "invalid arguments, expected {}", usage)
+            }
+            TemplateErrorKind::Pattern(kind) => write!(f, "{}", kind),
+            TemplateErrorKind::EmptyClass => write!(f, "{}", SamplerError::EmptyClass),
+            TemplateErrorKind::PatternTooLong => write!(f, "{}", SamplerError::TooLong),
+            TemplateErrorKind::UnreadableWordlist(err) => {
+                write!(f, "unable to read wordlist: {}", err)
+            }
+            TemplateErrorKind::EmptyWordlist => write!(f, "wordlist contains no words"),
+        }
+    }
+}
+
+const INT_USAGE: &str = "{int} or {int:lower:upper}";
+const FLOAT_USAGE: &str = "{float}, {float:lower:upper} or {float:lower:upper:.digits}";
+const WORD_USAGE: &str = "{word:path}";
+const STRING_USAGE: &str = "{string:pattern}";
+
+impl Template {
+    /// Parse a template, reading any wordlists it names. Literal braces are
+    /// written `{{` and `}}`. Inside a placeholder braces only need to be
+    /// balanced, so `{string:[A-Z]{3}}` is a single placeholder, and an
+    /// unbalanced one can be escaped with a backslash.
+    pub fn parse(template: &str, universe: &CharClass) -> Result<Template, TemplateError> {
+        parse_segments(template, universe).map_err(|e| TemplateError {
+            offset: template[..e.offset].chars().count(),
+            ..e
+        })
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        let mut line = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => line.push_str(text),
+                Segment::Int { lower, upper } => line.push_str(
+                    &sample::signed_integer_given_step(*lower, *upper, 1, rng).to_string(),
+                ),
+                Segment::Float {
+                    lower,
+                    upper,
+                    precision,
+                } => {
+                    let value = sample::float_given_bounds(*lower, *upper, rng);
+                    match precision {
+                        Some(precision) => {
+                            line.push_str(&sample::format_below(value, *precision, *upper))
+                        }
+                        None => line.push_str(&value.to_string()),
+                    }
+                }
+                Segment::Word(wordlist) => line.push_str(wordlist.sample(rng)),
+                Segment::String(sampler) => line.push_str(&sampler.sample(rng)),
+            }
+        }
+        line
+    }
+}
+
+fn parse_segments(template: &str, universe: &CharClass) -> Result<Template, TemplateError> {
+    let mut segments = vec![];
+    let mut literal = String::new();
+    let mut idx = 0;
+    while let Some(c) = template[idx..].chars().next() {
+        let next = template[idx + c.len_utf8()..].chars().next();
+        match (c, next) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                literal.push(c);
+                idx += 2;
+            }
+            ('}', _) => {
+                return Err(TemplateError::new(TemplateErrorKind::UnbalancedBrace, idx));
+            }
+            ('{', _) => {
+                let end = closing_brace(template, idx)
+                    .ok_or_else(|| TemplateError::new(TemplateErrorKind::UnbalancedBrace, idx))?;
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(
+                    parse_placeholder(&template[idx + 1..end], universe)
+                        .map_err(|e| TemplateError::new(e.kind, e.offset + idx + 1))?,
+                );
+                idx = end + 1;
+            }
+            _ => {
+                literal.push(c);
+                idx += c.len_utf8();
+            }
+        }
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(Template { segments })
+}
+
+/// Return the byte index of the brace closing the one at `start`, skipping
+/// nested pairs and escaped characters.
+fn closing_brace(template: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in template[start..].char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse the contents of a placeholder, a generator name optionally followed
+/// by a colon and its arguments. Offsets in errors are relative to the start
+/// of the contents.
+fn parse_placeholder(contents: &str, universe: &CharClass) -> Result<Segment, TemplateError> {
+    let (name, arguments) = match contents.find(':') {
+        Some(i) => (&contents[..i], Some(&contents[i + 1..])),
+        None => (contents, None),
+    };
+    let arguments_offset = name.len() + 1;
+    let invalid = |usage| TemplateError::new(TemplateErrorKind::InvalidArguments(usage), 0);
+    match name {
+        "int" => {
+            let bounds: Vec<&str> = arguments.map_or(vec!["0", "2"], |a| a.split(':').collect());
+            let (lower, upper) = match bounds[..] {
+                [lower, upper] => (lower.parse::<i128>(), upper.parse::<i128>()),
+                _ => return Err(invalid(INT_USAGE)),
+            };
+            match (lower, upper) {
+                (Ok(lower), Ok(upper)) if lower < upper => Ok(Segment::Int {
+                    lower,
+                    upper: upper - 1,
+                }),
+                _ => Err(invalid(INT_USAGE)),
+            }
+        }
+        "float" => {
+            let arguments: Vec<&str> = arguments.map_or(vec!["0", "1"], |a| a.split(':').collect());
+            let (bounds, precision) = match arguments[..] {
+                [lower, upper] => ((lower, upper), None),
+                [lower, upper, precision] => {
+                    let precision = precision
+                        .strip_prefix('.')
+                        .and_then(|digits| digits.parse::<usize>().ok())
+                        .ok_or_else(|| invalid(FLOAT_USAGE))?;
+                    ((lower, upper), Some(precision))
+                }
+                _ => return Err(invalid(FLOAT_USAGE)),
+            };
+            match (bounds.0.parse::<f64>(), bounds.1.parse::<f64>()) {
+                (Ok(lower), Ok(upper)) if lower < upper && (upper - lower).is_finite() => {
+                    Ok(Segment::Float {
+                        lower,
+                        upper,
+                        precision,
+                    })
+                }
+                _ => Err(invalid(FLOAT_USAGE)),
+            }
+        }
+        "word" => {
+            let path = arguments
+                .filter(|path| !path.is_empty())
+                .ok_or_else(|| invalid(WORD_USAGE))?;
+            let wordlist = Wordlist::from_file(path).map_err(|err| {
+                TemplateError::new(
+                    TemplateErrorKind::UnreadableWordlist(err.to_string()),
+                    arguments_offset,
+                )
+            })?;
+            if wordlist.is_empty() {
+                return Err(TemplateError::new(
+                    TemplateErrorKind::EmptyWordlist,
+                    arguments_offset,
+                ));
+            }
+            Ok(Segment::Word(wordlist))
+        }
+        "string" => {
+            let pattern = arguments.ok_or_else(|| invalid(STRING_USAGE))?;
+            let sampler = Pattern::parse_sampler(pattern, universe).map_err(|err| match err {
+                SamplerError::Parse(err) => {
+                    let offset = pattern
+                        .char_indices()
+                        .nth(err.offset)
+                        .map_or(pattern.len(), |(i, _)| i);
+                    TemplateError::new(
+                        TemplateErrorKind::Pattern(err.kind),
+                        arguments_offset + offset,
+                    )
+                }
+                SamplerError::EmptyClass => {
+                    TemplateError::new(TemplateErrorKind::EmptyClass, arguments_offset)
+                }
+                SamplerError::TooLong => {
+                    TemplateError::new(TemplateErrorKind::PatternTooLong, arguments_offset)
+                }
+            })?;
+            Ok(Segment::String(sampler))
+        }
+        _ => Err(TemplateError::new(TemplateErrorKind::UnknownGenerator, 0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    fn parse(template: &str) -> Result<Template, TemplateError> {
+        Template::parse(template, &CharClass::printable_ascii())
+    }
+
+    #[test]
+    fn placeholders_are_expanded() {
+        let template = parse("{int:1:4},{float:0:1:.2},{string:[A-Z]{3}}!").unwrap();
+        for _ in 0..100 {
+            let line = template.sample(&mut thread_rng());
+            let fields: Vec<&str> = line.split(',').collect();
+            assert!(["1", "2", "3"].contains(&fields[0]));
+            assert_eq!(fields[1].len(), 4);
+            assert!(fields[1].starts_with("0."));
+            assert_eq!(fields[2].len(), 4);
+            assert!(fields[2][..3].chars().all(|c| c.is_ascii_uppercase()));
+            assert!(fields[2].ends_with('!'));
+        }
+
+        let template = parse("{float:0.995:1:.2}").unwrap();
+        for _ in 0..100 {
+            assert_eq!(template.sample(&mut thread_rng()), "0.99");
+        }
+
+        let template = parse("{int:-3:-2} {float} {string:(a:0|b:1)\\}}").unwrap();
+        let line = template.sample(&mut thread_rng());
+        assert!(line.starts_with("-3 0"));
+        assert!(line.ends_with(" b}"));
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        let template = parse("{{id}}: {string:x} }} é").unwrap();
+        assert_eq!(template.sample(&mut thread_rng()), "{id}: x } é");
+        assert_eq!(parse("").unwrap().sample(&mut thread_rng()), "");
+    }
+
+    #[test]
+    fn word_placeholders_read_the_wordlist() {
+        let path = env::temp_dir().join(format!("rdg-template-{}.txt", std::process::id()));
+        fs::write(&path, "alpha\nbeta\n").unwrap();
+        let template = parse(&format!("<{{word:{}}}>", path.display())).unwrap();
+        for _ in 0..20 {
+            let line = template.sample(&mut thread_rng());
+            assert!(line == "<alpha>" || line == "<beta>");
+        }
+        fs::remove_file(&path).unwrap();
+
+        let err = parse("x{word:/nonexistent/rdg}").unwrap_err();
+        assert!(matches!(err.kind, TemplateErrorKind::UnreadableWordlist(_)));
+        assert_eq!(err.offset, 7);
+    }
+
+    #[test]
+    fn template_errors_report_kind_and_offset() {
+        let cases = vec![
+            ("ab{int", TemplateErrorKind::UnbalancedBrace, 2),
+            ("é}", TemplateErrorKind::UnbalancedBrace, 1),
+            ("{uuid}", TemplateErrorKind::UnknownGenerator, 1),
+            (
+                "{int:5:5}",
+                TemplateErrorKind::InvalidArguments(INT_USAGE),
+                1,
+            ),
+            ("{int:1}", TemplateErrorKind::InvalidArguments(INT_USAGE), 1),
+            (
+                "{float:0:1:2}",
+                TemplateErrorKind::InvalidArguments(FLOAT_USAGE),
+                1,
+            ),
+            ("{word}", TemplateErrorKind::InvalidArguments(WORD_USAGE), 1),
+            (
+                "{string}",
+                TemplateErrorKind::InvalidArguments(STRING_USAGE),
+                1,
+            ),
+            (
+                "ü {string:ab[c}",
+                TemplateErrorKind::Pattern(ParseErrorKind::UnbalancedBracket),
+                12,
+            ),
+            (
+                "{string:[^\\x00-\\u{10FFFF}]}",
+                TemplateErrorKind::EmptyClass,
+                8,
+            ),
+            ("{string:a{1048577}}", TemplateErrorKind::PatternTooLong, 8),
+        ];
+        for (template, kind, offset) in cases {
+            assert_eq!(
+                parse(template).unwrap_err(),
+                TemplateError { kind, offset },
+                "{}",
+                template
+            );
+        }
+    }
+}
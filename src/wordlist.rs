@@ -0,0 +1,709 @@
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::fs;
+use std::io;
+use std::vec;
+
+use rand::seq::index;
+use rand::Rng;
+use rand_distr::{Binomial, Distribution};
+
+use crate::sample::{self, AliasTable};
+
+/// The wordlists compiled into rdg, by name. `long` holds 7,776 words, one for
+/// each roll of five dice, and `short` holds common words of at most five
+/// letters.
+#[cfg(feature = "wordlists")]
+pub const BUILTIN_LISTS: &[(&str, &str)] = &[
+    ("long", include_str!("../wordlists/long.txt")),
+    ("short", include_str!("../wordlists/short.txt")),
+];
+
+#[cfg(not(feature = "wordlists"))]
+pub const BUILTIN_LISTS: &[(&str, &str)] = &[];
+
+/// A field of each line of a delimited file such as a TSV or CSV file. A
+/// field enclosed in double quotes may contain the delimiter, and a doubled
+/// quote inside it stands for one quote.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Column {
+    pub index: usize,
+    pub delimiter: char,
+}
+
+impl Column {
+    /// Return the field of a line at this column's index, counting from 0,
+    /// or None if the line has too few fields.
+    pub fn field<'a>(&self, line: &'a str) -> Option<Cow<'a, str>> {
+        let mut rest = line;
+        for _ in 0..self.index {
+            rest = split_field(rest, self.delimiter).1?;
+        }
+        Some(split_field(rest, self.delimiter).0)
+    }
+}
+
+/// Split the first field from the rest of a line, returning the field and
+/// whatever follows its delimiter, if there is one.
+fn split_field(line: &str, delimiter: char) -> (Cow<'_, str>, Option<&str>) {
+    let mut rest = match line.strip_prefix('"') {
+        Some(quoted) => quoted,
+        None => {
+            let (field, rest) = split_unquoted(line, delimiter);
+            return (Cow::Borrowed(field), rest);
+        }
+    };
+    let mut value = String::new();
+    while let Some(i) = rest.find('"') {
+        value.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        match rest.strip_prefix('"') {
+            Some(after) => {
+                value.push('"');
+                rest = after;
+            }
+            None => {
+                // Anything between the closing quote and the delimiter is kept.
+                let (tail, rest) = split_unquoted(rest, delimiter);
+                value.push_str(tail);
+                return (Cow::Owned(value), rest);
+            }
+        }
+    }
+    value.push_str(rest);
+    (Cow::Owned(value), None)
+}
+
+fn split_unquoted(line: &str, delimiter: char) -> (&str, Option<&str>) {
+    match line.find(delimiter) {
+        Some(i) => (&line[..i], Some(&line[i + delimiter.len_utf8()..])),
+        None => (line, None),
+    }
+}
+
+/// Split a line into the word before its last delimiter and the weight after
+/// it, then take the given column of the word if there is one. Return None if
+/// the word has too few fields, or an error if the weight is missing or is
+/// not a finite, non-negative number.
+pub fn weighted_word<'a>(
+    line: &'a str,
+    delimiter: char,
+    column: Option<&Column>,
+) -> io::Result<Option<(Cow<'a, str>, f64)>> {
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+    let (word, weight) = line
+        .rsplit_once(delimiter)
+        .ok_or_else(|| invalid(format!("line {:?} does not end in a weight", line)))?;
+    let weight = weight
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|w| (0.0..f64::INFINITY).contains(w))
+        .ok_or_else(|| {
+            invalid(format!(
+                "weight {:?} is not a finite, non-negative number",
+                weight
+            ))
+        })?;
+    let word = match column {
+        Some(column) => column.field(word),
+        None => Some(Cow::Borrowed(word)),
+    };
+    Ok(word.map(|word| (word, weight)))
+}
+
+/// The lines of a wordlist held in memory together with the byte span of
+/// each one, so that any word can be drawn in constant time however many
+/// values are requested.
+#[derive(Debug, PartialEq)]
+pub struct Wordlist {
+    text: String,
+    spans: Vec<(usize, usize)>,
+}
+
+impl Wordlist {
+    /// Read a whole file into memory and index the start and end of each of
+    /// its lines. Line endings, whether `\n` or `\r\n`, are not part of the
+    /// words.
+    pub fn from_file(path: &str) -> io::Result<Wordlist> {
+        let bytes = fs::read(path)?;
+        let text = String::from_utf8(bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(Wordlist::from_text(text))
+    }
+
+    pub fn from_text(text: String) -> Wordlist {
+        let mut spans = vec![];
+        let mut start = 0;
+        while start < text.len() {
+            let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+            spans.push((start, trim_carriage_return(&text, start, end)));
+            start = end + 1;
+        }
+        Wordlist { text, spans }
+    }
+
+    /// Return the built-in wordlist with the given name, if there is one.
+    pub fn builtin(name: &str) -> Option<Wordlist> {
+        BUILTIN_LISTS
+            .iter()
+            .find(|(list, _)| *list == name)
+            .map(|(_, text)| Wordlist::from_text(String::from(*text)))
+    }
+
+    pub fn new() -> Wordlist {
+        Wordlist {
+            text: String::new(),
+            spans: vec![],
+        }
+    }
+
+    /// Add the words of another wordlist after those of this one.
+    pub fn append(&mut self, other: Wordlist) {
+        let offset = self.text.len();
+        self.text.push_str(&other.text);
+        self.spans.extend(
+            other
+                .spans
+                .into_iter()
+                .map(|(start, end)| (start + offset, end + offset)),
+        );
+    }
+
+    /// Return a wordlist of the given field of each word, leaving out words
+    /// that have too few fields.
+    pub fn select(&self, column: &Column) -> Wordlist {
+        let mut selected = Wordlist::new();
+        for idx in 0..self.len() {
+            if let Some(field) = column.field(self.get(idx)) {
+                selected.push(&field);
+            }
+        }
+        selected
+    }
+
+    /// Return a wordlist of the words on each line, as read by
+    /// `weighted_word`, together with their weights.
+    pub fn split_weights(
+        &self,
+        delimiter: char,
+        column: Option<&Column>,
+    ) -> io::Result<(Wordlist, Vec<f64>)> {
+        let mut words = Wordlist::new();
+        let mut weights = vec![];
+        for idx in 0..self.len() {
+            if let Some((word, weight)) = weighted_word(self.get(idx), delimiter, column)? {
+                words.push(&word);
+                weights.push(weight);
+            }
+        }
+        Ok((words, weights))
+    }
+
+    fn push(&mut self, word: &str) {
+        let start = self.text.len();
+        self.text.push_str(word);
+        self.spans.push((start, self.text.len()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn get(&self, idx: usize) -> &str {
+        let (start, end) = self.spans[idx];
+        &self.text[start..end]
+    }
+
+    /// Return the entropy in bits of a word drawn by `sample`. This is the
+    /// base-2 logarithm of the number of lines when no two lines are the same,
+    /// and less otherwise.
+    pub fn entropy(&self) -> f64 {
+        let mut counts = HashMap::new();
+        for idx in 0..self.len() {
+            *counts.entry(self.get(idx)).or_insert(0usize) += 1;
+        }
+        let len = self.len() as f64;
+        counts
+            .values()
+            .map(|&count| count as f64 / len * (len / count as f64).log2())
+            .sum()
+    }
+
+    /// Return a word drawn uniformly from the list, which must not be empty.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> &str {
+        self.get(sample::random_index(self.len(), rng))
+    }
+
+    /// Return `words` words drawn by `sample`, joined by the separator.
+    pub fn passphrase<R: Rng + ?Sized>(
+        &self,
+        words: usize,
+        separator: &str,
+        rng: &mut R,
+    ) -> String {
+        let words: Vec<&str> = (0..words).map(|_| self.sample(rng)).collect();
+        words.join(separator)
+    }
+}
+
+fn trim_carriage_return(text: &str, start: usize, end: usize) -> usize {
+    if end > start && text.as_bytes()[end - 1] == b'\r' {
+        end - 1
+    } else {
+        end
+    }
+}
+
+/// Draws from a stream of items, written out one at a time.
+#[derive(Debug)]
+pub enum StreamSample<T> {
+    /// The draws themselves, in order.
+    Drawn(vec::IntoIter<T>),
+    /// Every item of a stream with no more items than draws asked for, kept
+    /// whole so that each draw is made as it is written.
+    Kept { items: Vec<T>, table: AliasTable },
+}
+
+impl<T: Clone> StreamSample<T> {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            StreamSample::Drawn(draws) => draws.len() == 0,
+            StreamSample::Kept { items, .. } => items.is_empty(),
+        }
+    }
+
+    /// Return the next draw, or None once the draws made in advance run out.
+    pub fn draw<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<T> {
+        match self {
+            StreamSample::Drawn(draws) => draws.next(),
+            StreamSample::Kept { items, table } => Some(items[table.sample(rng)].clone()),
+        }
+    }
+}
+
+/// Return `k` words drawn uniformly and independently from a stream of lines,
+/// reading it only once. This is `sample_weighted_stream` with every line
+/// given the same weight.
+pub fn sample_stream<I, R>(lines: I, k: usize, rng: &mut R) -> io::Result<StreamSample<String>>
+where
+    I: IntoIterator<Item = io::Result<String>>,
+    R: Rng + ?Sized,
+{
+    let items = lines.into_iter().map(|line| line.map(|line| (line, 1.0)));
+    sample_weighted_stream(items, k, rng)
+}
+
+/// Return the words on `k` distinct lines of a stream of lines, chosen
+/// uniformly and in random order, reading it only once. The `i`th line replaces a random
+/// one of the `k` words held so far with probability `k / i`. Return fewer
+/// than `k` words if the stream has fewer than `k` lines.
+pub fn sample_stream_distinct<I, R>(lines: I, k: usize, rng: &mut R) -> io::Result<Vec<String>>
+where
+    I: IntoIterator<Item = io::Result<String>>,
+    R: Rng + ?Sized,
+{
+    let mut reservoir = vec![];
+    for (i, line) in lines.into_iter().enumerate() {
+        let line = line?;
+        if i < k {
+            reservoir.push(line);
+            continue;
+        }
+        let slot = sample::random_index(i + 1, rng);
+        if slot < k {
+            reservoir[slot] = line;
+        }
+    }
+    sample::shuffle(&mut reservoir, rng);
+    Ok(reservoir)
+}
+
+/// Return `k` items drawn independently from a stream of weighted items,
+/// each with probability proportional to its weight, reading it only once
+/// and holding no more than about `k` items. Items are kept until there are
+/// more than `k` of them, and a stream that ends first is kept whole. Past that
+/// point each of `k` slots holds a draw from the items so far and keeps the
+/// `i`th item with its weight over the total weight of the first `i`, so after
+/// every item the number of slots to overwrite is binomial and the slots
+/// themselves are chosen uniformly. Items without a positive weight are
+/// skipped.
+pub fn sample_weighted_stream<T, I, R>(
+    items: I,
+    k: usize,
+    rng: &mut R,
+) -> io::Result<StreamSample<T>>
+where
+    T: Clone,
+    I: IntoIterator<Item = io::Result<(T, f64)>>,
+    R: Rng + ?Sized,
+{
+    let mut items = items.into_iter();
+    let mut kept = vec![];
+    let mut weights = vec![];
+    for item in &mut items {
+        let (item, weight) = item?;
+        if weight == 0.0 {
+            continue;
+        }
+        kept.push(item);
+        weights.push(weight);
+        if kept.len() > k {
+            break;
+        }
+    }
+    let table = match AliasTable::new(&weights) {
+        Some(table) => table,
+        None => return Ok(StreamSample::Drawn(vec![].into_iter())),
+    };
+    if kept.len() <= k {
+        return Ok(StreamSample::Kept { items: kept, table });
+    }
+    let mut reservoir: Vec<T> = (0..k).map(|_| kept[table.sample(rng)].clone()).collect();
+    let mut total: f64 = weights.iter().sum();
+    drop(kept);
+    for item in items {
+        let (item, weight) = item?;
+        if weight == 0.0 {
+            continue;
+        }
+        total += weight;
+        let replacements = Binomial::new(k as u64, weight / total).unwrap().sample(rng) as usize;
+        for slot in index::sample(rng, k, replacements) {
+            reservoir[slot] = item.clone();
+        }
+    }
+    Ok(StreamSample::Drawn(reservoir.into_iter()))
+}
+
+/// Return `k` distinct items of a stream of weighted items, drawn one after
+/// another with probability proportional to weight, reading it only once.
+/// This is the A-Res algorithm of Efraimidis and Spirakis: each item gets
+/// the key `ln(u) / weight` for a uniform `u`, and the `k` items with the
+/// largest keys are kept and returned in order of decreasing key. Return
+/// fewer than `k` items if fewer have a positive weight.
+pub fn sample_weighted_stream_distinct<T, I, R>(
+    items: I,
+    k: usize,
+    rng: &mut R,
+) -> io::Result<Vec<T>>
+where
+    I: IntoIterator<Item = io::Result<(T, f64)>>,
+    R: Rng + ?Sized,
+{
+    let mut heap = BinaryHeap::new();
+    for item in items {
+        let (item, weight) = item?;
+        if weight == 0.0 || k == 0 {
+            continue;
+        }
+        let key = sample::random_uniform(rng).ln() / weight;
+        if heap.len() < k {
+            heap.push(Reverse(Keyed { key, item }));
+        } else if heap.peek().is_some_and(|Reverse(least)| key > least.key) {
+            heap.pop();
+            heap.push(Reverse(Keyed { key, item }));
+        }
+    }
+    Ok(heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(keyed)| keyed.item)
+        .collect())
+}
+
+/// An item ordered by its key alone.
+struct Keyed<T> {
+    key: f64,
+    item: T,
+}
+
+impl<T> PartialEq for Keyed<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Keyed<T> {}
+
+impl<T> PartialOrd for Keyed<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Keyed<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.total_cmp(&other.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::BufRead;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn from_text_indexes_lines() {
+        let wordlist = Wordlist::from_text(String::from("apple\nbanana\r\n\ncherry"));
+        assert_eq!(wordlist.len(), 4);
+        assert_eq!(wordlist.get(0), "apple");
+        assert_eq!(wordlist.get(1), "banana");
+        assert_eq!(wordlist.get(2), "");
+        assert_eq!(wordlist.get(3), "cherry");
+
+        let wordlist = Wordlist::from_text(String::from("日本\nß\n"));
+        assert_eq!(wordlist.len(), 2);
+        assert_eq!(wordlist.get(0), "日本");
+        assert_eq!(wordlist.get(1), "ß");
+
+        assert!(Wordlist::from_text(String::new()).is_empty());
+    }
+
+    #[test]
+    fn append_and_select_build_new_wordlists() {
+        let mut wordlist = Wordlist::from_text(String::from("a\tx\nb\ty\n"));
+        wordlist.append(Wordlist::from_text(String::from("c\tz\r\nd")));
+        assert_eq!(wordlist.len(), 4);
+        assert_eq!(wordlist.get(2), "c\tz");
+        assert_eq!(wordlist.get(3), "d");
+
+        let column = Column {
+            index: 1,
+            delimiter: '\t',
+        };
+        let selected = wordlist.select(&column);
+        assert_eq!(selected.len(), 3);
+        assert_eq!(
+            (0..3).map(|idx| selected.get(idx)).collect::<Vec<_>>(),
+            vec!["x", "y", "z"]
+        );
+    }
+
+    #[test]
+    fn column_extracts_fields() {
+        let csv = |index| Column {
+            index,
+            delimiter: ',',
+        };
+        assert_eq!(csv(0).field("a,b,c").as_deref(), Some("a"));
+        assert_eq!(csv(2).field("a,b,c").as_deref(), Some("c"));
+        assert_eq!(csv(3).field("a,b,c"), None);
+        assert_eq!(csv(1).field("a,,c").as_deref(), Some(""));
+        assert_eq!(csv(0).field("").as_deref(), Some(""));
+        assert_eq!(
+            csv(1).field(r#"1,"Doe, ""Jo""",x"#).as_deref(),
+            Some(r#"Doe, "Jo""#)
+        );
+        assert_eq!(csv(2).field(r#"1,"a,b",x"#).as_deref(), Some("x"));
+        assert_eq!(csv(0).field(r#""ab"c,d"#).as_deref(), Some("abc"));
+        assert_eq!(
+            csv(0).field(r#""unterminated,d"#).as_deref(),
+            Some("unterminated,d")
+        );
+        assert_eq!(csv(1).field(r#""unterminated,d"#), None);
+
+        let column = Column {
+            index: 1,
+            delimiter: '→',
+        };
+        assert_eq!(column.field("日→本→語").as_deref(), Some("本"));
+    }
+
+    #[test]
+    fn weighted_words_are_split_from_weights() {
+        assert_eq!(
+            weighted_word("a\tb\t2.5", '\t', None).unwrap(),
+            Some((Cow::Borrowed("a\tb"), 2.5))
+        );
+        let column = Column {
+            index: 1,
+            delimiter: ',',
+        };
+        assert_eq!(
+            weighted_word("1,\"x,y\",3", ',', Some(&column)).unwrap(),
+            Some((Cow::Borrowed("x,y"), 3.0))
+        );
+        assert_eq!(weighted_word("1,3", ',', Some(&column)).unwrap(), None);
+        assert!(weighted_word("a", '\t', None).is_err());
+        assert!(weighted_word("a\t-1", '\t', None).is_err());
+        assert!(weighted_word("a\tinf", '\t', None).is_err());
+        assert!(weighted_word("a\tmany", '\t', None).is_err());
+
+        let wordlist = Wordlist::from_text(String::from("a\t1\nb\t0\n"));
+        let (words, weights) = wordlist.split_weights('\t', None).unwrap();
+        assert_eq!((words.get(0), words.get(1)), ("a", "b"));
+        assert_eq!(weights, vec![1.0, 0.0]);
+        assert!(Wordlist::from_text(String::from("a\n"))
+            .split_weights('\t', None)
+            .is_err());
+    }
+
+    #[test]
+    fn weighted_stream_reservoirs_follow_weights() {
+        let items = || vec![("a", 6.0), ("b", 0.0), ("c", 3.0), ("d", 1.0)];
+        let mut counts = HashMap::new();
+        for _ in 0..100 {
+            let sample =
+                sample_weighted_stream(items().into_iter().map(Ok), 100, &mut thread_rng());
+            let words = draws(sample.unwrap(), 100);
+            assert_eq!(words.len(), 100);
+            for word in words {
+                *counts.entry(word).or_insert(0) += 1;
+            }
+        }
+        assert_eq!(counts.get("b"), None);
+        assert!((5700..6300).contains(&counts["a"]));
+        assert!((2700..3300).contains(&counts["c"]));
+        assert!((800..1200).contains(&counts["d"]));
+
+        let mut firsts = HashMap::new();
+        for _ in 0..10000 {
+            let words =
+                sample_weighted_stream_distinct(items().into_iter().map(Ok), 3, &mut thread_rng())
+                    .unwrap();
+            let mut sorted = words.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, vec!["a", "c", "d"]);
+            *firsts.entry(words[0]).or_insert(0) += 1;
+        }
+        assert!((5700..6300).contains(&firsts["a"]));
+        assert!((2700..3300).contains(&firsts["c"]));
+        assert!((800..1200).contains(&firsts["d"]));
+
+        let words =
+            sample_weighted_stream_distinct(items().into_iter().map(Ok), 5, &mut thread_rng())
+                .unwrap();
+        assert_eq!(words.len(), 3);
+        let words = sample_weighted_stream(vec![Ok(("z", 0.0))], 5, &mut thread_rng()).unwrap();
+        assert!(words.is_empty());
+    }
+
+    #[test]
+    fn entropy_counts_distinct_words() {
+        assert_eq!(
+            Wordlist::from_text(String::from("a\nb\nc\nd")).entropy(),
+            2.0
+        );
+        assert_eq!(
+            Wordlist::from_text(String::from("a\na\nb\nb")).entropy(),
+            1.0
+        );
+        assert_eq!(Wordlist::from_text(String::from("a\na")).entropy(), 0.0);
+        assert_eq!(Wordlist::new().entropy(), 0.0);
+    }
+
+    #[cfg(feature = "wordlists")]
+    #[test]
+    fn builtin_lists_are_sorted_and_distinct() {
+        for (name, _) in BUILTIN_LISTS {
+            let wordlist = Wordlist::builtin(name).unwrap();
+            assert!(wordlist.len() > 1000);
+            let words: Vec<&str> = (0..wordlist.len()).map(|idx| wordlist.get(idx)).collect();
+            assert!(words.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(words
+                .iter()
+                .all(|word| word.chars().all(|c| c.is_ascii_lowercase())));
+        }
+        assert_eq!(Wordlist::builtin("long").unwrap().len(), 7776);
+        assert_eq!(Wordlist::builtin("missing"), None);
+    }
+
+    #[test]
+    fn wordlist_samples_every_word() {
+        let wordlist = Wordlist::from_text(String::from("a\nb\nc\n"));
+        let mut counts = HashMap::new();
+        for _ in 0..3000 {
+            *counts
+                .entry(wordlist.sample(&mut thread_rng()))
+                .or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 3);
+        assert!(counts.values().all(|&c| (850..1150).contains(&c)));
+    }
+
+    #[test]
+    fn passphrases_draw_from_the_selected_column() {
+        let wordlist = Wordlist::from_text(String::from("11111\tabacus\n11112\tabdomen\n"));
+        let column = Column {
+            index: 1,
+            delimiter: '\t',
+        };
+        let words = wordlist.select(&column);
+        for _ in 0..100 {
+            let passphrase = words.passphrase(4, "-", &mut thread_rng());
+            let parts: Vec<&str> = passphrase.split('-').collect();
+            assert_eq!(parts.len(), 4);
+            assert!(parts.iter().all(|&w| w == "abacus" || w == "abdomen"));
+        }
+        assert_eq!(words.passphrase(0, "-", &mut thread_rng()), "");
+    }
+
+    fn draws<T: Clone>(mut sample: StreamSample<T>, k: usize) -> Vec<T> {
+        (0..k)
+            .map_while(|_| sample.draw(&mut thread_rng()))
+            .collect()
+    }
+
+    #[test]
+    fn stream_reservoir_is_uniform() {
+        let text = "a\nb\nc\nd\n";
+        for (k, runs) in [(100, 100), (2, 5000)] {
+            let mut counts = HashMap::new();
+            for _ in 0..runs {
+                let sample = sample_stream(text.as_bytes().lines(), k, &mut thread_rng());
+                let words = draws(sample.unwrap(), k);
+                assert_eq!(words.len(), k);
+                for word in words {
+                    *counts.entry(word).or_insert(0) += 1;
+                }
+            }
+            assert_eq!(counts.len(), 4);
+            assert!(counts.values().all(|&c| (2300..2700).contains(&c)));
+        }
+    }
+
+    #[test]
+    fn stream_reservoir_handles_short_streams() {
+        let mut rng = thread_rng();
+        assert!(sample_stream("".as_bytes().lines(), 5, &mut rng)
+            .unwrap()
+            .is_empty());
+        let only = sample_stream("only\n".as_bytes().lines(), usize::MAX, &mut rng).unwrap();
+        assert_eq!(draws(only, 3), vec!["only", "only", "only"]);
+        assert!(sample_stream("a\nb\n".as_bytes().lines(), 0, &mut rng)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn distinct_stream_reservoir_is_uniform() {
+        let text = "a\nb\nc\nd\ne\n";
+        let mut counts = HashMap::new();
+        for _ in 0..2500 {
+            let mut words =
+                sample_stream_distinct(text.as_bytes().lines(), 2, &mut thread_rng()).unwrap();
+            words.sort();
+            words.dedup();
+            assert_eq!(words.len(), 2);
+            for word in words {
+                *counts.entry(word).or_insert(0) += 1;
+            }
+        }
+        assert_eq!(counts.len(), 5);
+        assert!(counts.values().all(|&c| (850..1150).contains(&c)));
+
+        let words =
+            sample_stream_distinct("a\nb\n".as_bytes().lines(), 3, &mut thread_rng()).unwrap();
+        assert_eq!(words.len(), 2);
+    }
+}